@@ -0,0 +1,451 @@
+use anyhow::{Context, Result, bail};
+use flate2::read::GzDecoder;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+
+use crate::format::MAX_SNIFF_BYTES;
+use crate::utils::is_config_content;
+
+/// Marker file deleting a path from lower layers: `.wh.<name>`.
+const WHITEOUT_PREFIX: &str = ".wh.";
+/// Marker file hiding everything a lower layer put in its directory.
+const OPAQUE_WHITEOUT: &str = ".wh..wh..opq";
+/// How many nested OCI indexes (e.g. multi-platform images) to follow.
+const MAX_INDEX_DEPTH: usize = 4;
+
+/// Whether the walker should open this file as an archive.
+pub fn is_archive(p: &Path) -> bool {
+    let name = p
+        .file_name()
+        .and_then(|s| s.to_str())
+        .unwrap_or("")
+        .to_ascii_lowercase();
+    [".tar", ".tar.gz", ".tgz", ".zip"]
+        .iter()
+        .any(|ext| name.ends_with(ext))
+}
+
+/// Read the config files inside an archive, keyed by their path inside it.
+/// Members are recognised like files on disk, by name or, without an extension, by content.
+///
+/// Container image tarballs (`docker save` or OCI image layout) are flattened:
+/// layers are applied in order, honouring whiteouts, and the resulting
+/// filesystem is returned instead of the tarball's own entries.
+/// Archives nested inside archives are not opened.
+pub fn read_members(p: &Path) -> Result<BTreeMap<String, Vec<u8>>> {
+    let is_zip = p
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("zip"));
+    if is_zip {
+        return read_zip(p);
+    }
+
+    let listing = list_tar(p)?;
+    match image_layers(p, &listing)? {
+        Some(layers) => flatten_layers(p, &layers),
+        None => Ok(listing.files),
+    }
+}
+
+fn read_zip(p: &Path) -> Result<BTreeMap<String, Vec<u8>>> {
+    let mut archive = zip::ZipArchive::new(File::open(p)?)
+        .with_context(|| format!("Failed to open zip archive: {}", p.display()))?;
+    let mut files = BTreeMap::new();
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i)?;
+        let name = normalize(&entry.name()?);
+        if !entry.is_file() {
+            continue;
+        }
+        let size = entry.size();
+        if let Some(content) = read_config(&name, size, &mut entry)? {
+            files.insert(name, content);
+        }
+    }
+    Ok(files)
+}
+
+/// The content of an archive member if it is a config file, see `is_config_content`.
+fn read_config(name: &str, size: u64, mut reader: impl Read) -> Result<Option<Vec<u8>>> {
+    let mut sniffed = None;
+    let wanted = is_config_content(Path::new(name), || {
+        sniffed = read_small(&mut reader, size);
+        sniffed.clone()
+    });
+    if !wanted {
+        return Ok(None);
+    }
+    if let Some(content) = sniffed {
+        return Ok(Some(content));
+    }
+    let mut content = Vec::new();
+    reader.read_to_end(&mut content)?;
+    Ok(Some(content))
+}
+
+/// The whole member, unless it is too large to sniff.
+fn read_small(reader: &mut impl Read, size: u64) -> Option<Vec<u8>> {
+    if size > MAX_SNIFF_BYTES {
+        return None;
+    }
+    let mut content = Vec::new();
+    reader.read_to_end(&mut content).ok()?;
+    Some(content)
+}
+
+/// Config files of a plain tarball, plus the metadata files that mark an image.
+#[derive(Default)]
+struct TarListing {
+    files: BTreeMap<String, Vec<u8>>,
+    docker_manifest: Option<Vec<u8>>,
+    oci_index: Option<Vec<u8>>,
+    oci_layout: bool,
+}
+
+fn list_tar(p: &Path) -> Result<TarListing> {
+    let mut listing = TarListing::default();
+    let mut archive = open_tar(p)?;
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let name = normalize(&entry.path()?.to_string_lossy());
+        if name == "oci-layout" {
+            listing.oci_layout = true;
+            continue;
+        }
+        let size = entry.size();
+        let Some(content) = read_config(&name, size, &mut entry)? else {
+            continue;
+        };
+        match name.as_str() {
+            "manifest.json" => listing.docker_manifest = Some(content.clone()),
+            "index.json" => listing.oci_index = Some(content.clone()),
+            _ => {}
+        }
+        listing.files.insert(name, content);
+    }
+    Ok(listing)
+}
+
+/// `manifest.json` written by `docker save`.
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct DockerManifest {
+    layers: Vec<String>,
+}
+
+/// An OCI index or image manifest; only the fields needed to find layers.
+#[derive(Deserialize)]
+struct OciDocument {
+    #[serde(default)]
+    manifests: Vec<OciDescriptor>,
+    #[serde(default)]
+    layers: Vec<OciDescriptor>,
+}
+
+#[derive(Deserialize)]
+struct OciDescriptor {
+    digest: String,
+}
+
+/// Layer entry names in application order, or None if the tarball is not an image.
+/// With several images in one `docker save`, the first one is used.
+fn image_layers(p: &Path, listing: &TarListing) -> Result<Option<Vec<String>>> {
+    if let Some(manifest) = &listing.docker_manifest
+        && let Ok(images) = serde_json::from_slice::<Vec<DockerManifest>>(manifest)
+        && let Some(image) = images.into_iter().next()
+    {
+        return Ok(Some(image.layers.iter().map(|l| normalize(l)).collect()));
+    }
+
+    let (true, Some(index)) = (listing.oci_layout, &listing.oci_index) else {
+        return Ok(None);
+    };
+    let mut document: OciDocument = serde_json::from_slice(index)
+        .with_context(|| format!("Invalid OCI index in {}", p.display()))?;
+    for _ in 0..MAX_INDEX_DEPTH {
+        if !document.layers.is_empty() {
+            return Ok(Some(
+                document
+                    .layers
+                    .iter()
+                    .map(|l| blob_path(&l.digest))
+                    .collect(),
+            ));
+        }
+        let Some(first) = document.manifests.first() else {
+            break;
+        };
+        let blob_name = blob_path(&first.digest);
+        let blob = read_tar_entry(p, &blob_name)?
+            .with_context(|| format!("Missing blob {} in {}", blob_name, p.display()))?;
+        document = serde_json::from_slice(&blob)
+            .with_context(|| format!("Invalid OCI manifest {} in {}", blob_name, p.display()))?;
+    }
+    bail!("No image manifest found in OCI layout: {}", p.display())
+}
+
+fn blob_path(digest: &str) -> String {
+    format!("blobs/{}", digest.replacen(':', "/", 1))
+}
+
+fn read_tar_entry(p: &Path, wanted: &str) -> Result<Option<Vec<u8>>> {
+    let mut archive = open_tar(p)?;
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if normalize(&entry.path()?.to_string_lossy()) == wanted {
+            let mut content = Vec::new();
+            entry.read_to_end(&mut content)?;
+            return Ok(Some(content));
+        }
+    }
+    Ok(None)
+}
+
+/// Config files added and paths deleted by a single image layer.
+#[derive(Default)]
+struct LayerChanges {
+    files: Vec<(String, Vec<u8>)>,
+    whiteouts: Vec<String>,
+    opaque_dirs: Vec<String>,
+}
+
+impl LayerChanges {
+    /// Apply this layer on top of the filesystem built from the layers below.
+    fn apply(&self, fs: &mut BTreeMap<String, Vec<u8>>) {
+        for dir in &self.opaque_dirs {
+            remove_under(fs, dir);
+        }
+        for path in &self.whiteouts {
+            fs.remove(path);
+            remove_under(fs, path);
+        }
+        for (path, content) in &self.files {
+            fs.insert(path.clone(), content.clone());
+        }
+    }
+}
+
+fn flatten_layers(p: &Path, layers: &[String]) -> Result<BTreeMap<String, Vec<u8>>> {
+    let wanted: HashSet<&str> = layers.iter().map(String::as_str).collect();
+    let mut changes: HashMap<String, LayerChanges> = HashMap::new();
+
+    let mut archive = open_tar(p)?;
+    for entry in archive.entries()? {
+        let entry = entry?;
+        let name = normalize(&entry.path()?.to_string_lossy());
+        if !wanted.contains(name.as_str()) || !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let layer = read_layer(entry)
+            .with_context(|| format!("Failed to read layer {} in {}", name, p.display()))?;
+        changes.insert(name, layer);
+    }
+
+    let mut fs = BTreeMap::new();
+    for layer in layers {
+        changes
+            .get(layer)
+            .with_context(|| format!("Layer {} missing from {}", layer, p.display()))?
+            .apply(&mut fs);
+    }
+    Ok(fs)
+}
+
+fn read_layer<R: Read>(reader: R) -> Result<LayerChanges> {
+    let mut changes = LayerChanges::default();
+    let mut archive = tar::Archive::new(decompress(BufReader::new(reader))?);
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let name = normalize(&entry.path()?.to_string_lossy());
+        let (dir, file_name) = name.rsplit_once('/').unwrap_or(("", name.as_str()));
+
+        if file_name == OPAQUE_WHITEOUT {
+            changes.opaque_dirs.push(dir.to_string());
+        } else if let Some(target) = file_name.strip_prefix(WHITEOUT_PREFIX) {
+            changes.whiteouts.push(join(dir, target));
+        } else if entry.header().entry_type().is_file() {
+            let size = entry.size();
+            if let Some(content) = read_config(&name, size, &mut entry)? {
+                changes.files.push((name, content));
+            }
+        }
+    }
+    Ok(changes)
+}
+
+fn remove_under(fs: &mut BTreeMap<String, Vec<u8>>, dir: &str) {
+    if dir.is_empty() {
+        fs.clear();
+        return;
+    }
+    let prefix = format!("{}/", dir);
+    fs.retain(|path, _| !path.starts_with(&prefix));
+}
+
+fn open_tar(p: &Path) -> Result<tar::Archive<Box<dyn Read>>> {
+    let file = File::open(p).with_context(|| format!("Failed to open {}", p.display()))?;
+    Ok(tar::Archive::new(decompress(BufReader::new(file))?))
+}
+
+/// Wrap a reader in a gzip decoder when its content starts with the gzip magic bytes.
+fn decompress<'a, R: BufRead + 'a>(mut reader: R) -> Result<Box<dyn Read + 'a>> {
+    let is_gzip = reader.fill_buf()?.starts_with(&[0x1f, 0x8b]);
+    Ok(if is_gzip {
+        Box::new(GzDecoder::new(reader))
+    } else {
+        Box::new(reader)
+    })
+}
+
+/// Strip `./` and leading slashes so paths read like `etc/app/config.yaml`.
+fn normalize(name: &str) -> String {
+    name.trim_start_matches("./")
+        .trim_start_matches('/')
+        .to_string()
+}
+
+fn join(dir: &str, name: &str) -> String {
+    if dir.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}/{}", dir, name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+    use std::io::Write;
+
+    fn tar_bytes(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (name, content) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, name, *content).unwrap();
+        }
+        builder.into_inner().unwrap()
+    }
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn names(members: &BTreeMap<String, Vec<u8>>) -> Vec<&str> {
+        members.keys().map(String::as_str).collect()
+    }
+
+    #[test]
+    fn test_is_archive() {
+        assert!(is_archive(Path::new("bundle.tar.gz")));
+        assert!(is_archive(Path::new("bundle.TGZ")));
+        assert!(is_archive(Path::new("artifact.zip")));
+        assert!(is_archive(Path::new("image.tar")));
+        assert!(!is_archive(Path::new("config.yaml")));
+    }
+
+    #[test]
+    fn test_tar_gz_members() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bundle.tar.gz");
+        let tar = tar_bytes(&[
+            ("./etc/app/config.yaml", b"port: 80\n"),
+            ("./etc/app/README.md", b"# docs"),
+            ("./etc/app/Appfile", b"region: eu-west-1\nreplicas: 2\n"),
+            ("./bin/tool", b"\x7fELF\0\0"),
+            ("settings.json", b"{}"),
+        ]);
+        std::fs::write(&path, gzip(&tar)).unwrap();
+
+        // Files without an extension are sniffed, as on disk
+        let members = read_members(&path).unwrap();
+        assert_eq!(
+            names(&members),
+            vec!["etc/app/Appfile", "etc/app/config.yaml", "settings.json"]
+        );
+        assert_eq!(members["etc/app/config.yaml"], b"port: 80\n");
+    }
+
+    #[test]
+    fn test_zip_members() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("artifact.zip");
+        let mut writer = zip::ZipWriter::new(File::create(&path).unwrap());
+        let options = zip::write::SimpleFileOptions::default();
+        writer.start_file("conf/app.toml", options).unwrap();
+        writer.write_all(b"a = 1\n").unwrap();
+        writer.start_file("bin/tool", options).unwrap();
+        writer.write_all(b"\x7fELF").unwrap();
+        writer.start_file("conf/Settings", options).unwrap();
+        writer.write_all(br#"{"debug": false}"#).unwrap();
+        writer.finish().unwrap();
+
+        let members = read_members(&path).unwrap();
+        assert_eq!(names(&members), vec!["conf/Settings", "conf/app.toml"]);
+    }
+
+    #[test]
+    fn test_docker_save_layers_with_whiteouts() {
+        let base = tar_bytes(&[
+            ("etc/app/config.yaml", b"v: 1\n"),
+            ("etc/app/old.yaml", b"old: true\n"),
+            ("etc/cache/a.json", b"{}"),
+        ]);
+        let update = gzip(&tar_bytes(&[
+            ("etc/app/config.yaml", b"v: 2\n"),
+            ("etc/app/.wh.old.yaml", b""),
+            ("etc/cache/.wh..wh..opq", b""),
+            ("etc/cache/b.json", b"{}"),
+        ]));
+        let manifest = br#"[{"Config":"cfg.json","RepoTags":["app:1"],"Layers":["l1/layer.tar","l2/layer.tar"]}]"#;
+        let image = tar_bytes(&[
+            ("l1/layer.tar", &base),
+            ("l2/layer.tar", &update),
+            ("manifest.json", manifest),
+        ]);
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("image.tar");
+        std::fs::write(&path, image).unwrap();
+
+        let members = read_members(&path).unwrap();
+        assert_eq!(
+            names(&members),
+            vec!["etc/app/config.yaml", "etc/cache/b.json"]
+        );
+        assert_eq!(members["etc/app/config.yaml"], b"v: 2\n");
+    }
+
+    #[test]
+    fn test_oci_layout_layers() {
+        let layer = gzip(&tar_bytes(&[
+            ("etc/app.yaml", b"a: 1\n"),
+            ("etc/caddy/Caddyfile", b"<config/>"),
+        ]));
+        let manifest = br#"{"schemaVersion":2,"layers":[{"digest":"sha256:layer1"}]}"#;
+        let index = br#"{"schemaVersion":2,"manifests":[{"digest":"sha256:manifest1"}]}"#;
+        let image = tar_bytes(&[
+            ("oci-layout", br#"{"imageLayoutVersion":"1.0.0"}"#),
+            ("index.json", index),
+            ("blobs/sha256/manifest1", manifest),
+            ("blobs/sha256/layer1", &layer),
+        ]);
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("image.tar");
+        std::fs::write(&path, image).unwrap();
+
+        let members = read_members(&path).unwrap();
+        assert_eq!(names(&members), vec!["etc/app.yaml", "etc/caddy/Caddyfile"]);
+    }
+}
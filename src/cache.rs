@@ -0,0 +1,429 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+
+use crate::models::SecretFinding;
+use crate::policy::models::Violation;
+use crate::policy::parser::{FlatMap, SourceMap};
+use crate::source::{ARCHIVE_SEPARATOR, ConfigFile, backing_file};
+use crate::utils::hash_flat_map;
+
+/// Bumped whenever the cached data changes shape, discarding old cache files.
+const CACHE_VERSION: u32 = 11;
+
+/// Per-file scan results, shared by `scan`, `secrets`, `policy check` and `report`.
+///
+/// Entries are keyed by path and trusted while size and mtime are unchanged.
+/// When they differ the file is re-hashed; if the content hash still matches
+/// (e.g. after a `touch`) the parsed keys and findings are kept.
+/// Archive members are already in memory, so they are always matched by hash.
+///
+/// Without a cache file the results are only kept in memory for the current run,
+/// which still avoids re-reading files when `report` runs several scanners.
+pub struct Cache {
+    path: Option<PathBuf>,
+    state: Mutex<CacheState>,
+}
+
+#[derive(Default)]
+struct CacheState {
+    files: HashMap<String, CachedFile>,
+    dirty: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CacheFile {
+    version: u32,
+    /// Fingerprint of the secret pattern set the cached findings were produced with.
+    secret_patterns: String,
+    /// Fingerprint of the format settings the cached parses were produced with.
+    #[serde(default)]
+    formats: String,
+    files: HashMap<String, CachedFile>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct CachedFile {
+    size: u64,
+    mtime_ns: u64,
+    hash: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    parsed: Option<CachedParse>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    secrets: Option<Vec<SecretFinding>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    violations: Option<CachedViolations>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "lowercase")]
+enum CachedParse {
+    Keys {
+        keys: FlatMap,
+        canonical_hash: String,
+        #[serde(default)]
+        source_map: SourceMap,
+    },
+    Error(String),
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct CachedViolations {
+    /// Hash of the policy the violations were evaluated against.
+    policy: String,
+    violations: Vec<Violation>,
+}
+
+impl Cache {
+    /// Open a cache backed by `path`, or an in-memory one when `path` is None.
+    /// A missing, unreadable or outdated cache file starts an empty cache.
+    pub fn open(path: Option<&str>) -> Result<Cache> {
+        let mut state = CacheState::default();
+        if let Some(p) = path
+            && let Ok(data) = fs::read(p)
+        {
+            match serde_json::from_slice::<CacheFile>(&data) {
+                Ok(file) if file.version == CACHE_VERSION => {
+                    state.files = file.files;
+                    if file.secret_patterns != crate::secrets::patterns_fingerprint() {
+                        for entry in state.files.values_mut() {
+                            entry.secrets = None;
+                        }
+                        state.dirty = true;
+                    }
+                    if file.formats != crate::format::settings_fingerprint() {
+                        for entry in state.files.values_mut() {
+                            entry.parsed = None;
+                            entry.violations = None;
+                        }
+                        state.dirty = true;
+                    }
+                }
+                Ok(_) => state.dirty = true,
+                Err(e) => {
+                    eprintln!("Warning: Ignoring unreadable cache {}: {}", p, e);
+                    state.dirty = true;
+                }
+            }
+        }
+        Ok(Cache {
+            path: path.map(PathBuf::from),
+            state: Mutex::new(state),
+        })
+    }
+
+    /// SHA-256 of the file's raw content.
+    pub fn hash(&self, p: &ConfigFile) -> Result<String> {
+        Ok(self.entry(p)?.hash)
+    }
+
+    /// Flattened key map of the file, parsed once per content hash.
+    pub fn parse(&self, p: &ConfigFile) -> Result<FlatMap> {
+        match self.parsed(p)? {
+            CachedParse::Keys { keys, .. } => Ok(keys),
+            CachedParse::Error(e) => Err(anyhow::anyhow!(e)),
+        }
+    }
+
+    /// Canonical hash of the file's parsed content, None if it does not parse.
+    pub fn canonical_hash(&self, p: &ConfigFile) -> Result<Option<String>> {
+        Ok(match self.parsed(p)? {
+            CachedParse::Keys { canonical_hash, .. } => Some(canonical_hash),
+            CachedParse::Error(_) => None,
+        })
+    }
+
+    /// Line and column of each flattened key, empty if the file does not parse.
+    pub fn source_map(&self, p: &ConfigFile) -> Result<SourceMap> {
+        Ok(match self.parsed(p)? {
+            CachedParse::Keys { source_map, .. } => source_map,
+            CachedParse::Error(_) => SourceMap::default(),
+        })
+    }
+
+    /// Secret findings for the file, running `detect` on a cache miss.
+    pub fn secrets(
+        &self,
+        p: &ConfigFile,
+        detect: impl FnOnce() -> Result<Vec<SecretFinding>>,
+    ) -> Result<Vec<SecretFinding>> {
+        if let Some(findings) = self.entry(p)?.secrets {
+            return Ok(findings);
+        }
+        let findings = detect()?;
+        self.update(p, |e| e.secrets = Some(findings.clone()));
+        Ok(findings)
+    }
+
+    /// Policy violations for the file, running `evaluate` when the file or the policy changed.
+    pub fn violations(
+        &self,
+        p: &ConfigFile,
+        policy_hash: &str,
+        evaluate: impl FnOnce() -> Vec<Violation>,
+    ) -> Result<Vec<Violation>> {
+        if let Some(cached) = self.entry(p)?.violations
+            && cached.policy == policy_hash
+        {
+            return Ok(cached.violations);
+        }
+        let violations = evaluate();
+        self.update(p, |e| {
+            e.violations = Some(CachedViolations {
+                policy: policy_hash.to_string(),
+                violations: violations.clone(),
+            })
+        });
+        Ok(violations)
+    }
+
+    /// Write the cache back to disk if anything changed.
+    /// Entries for files (or archives) that no longer exist are dropped.
+    pub fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let mut state = self.lock();
+        if !state.dirty {
+            return Ok(());
+        }
+        state.files.retain(|p, _| backing_file(p).is_file());
+
+        if let Some(parent) = path.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            fs::create_dir_all(parent).with_context(|| {
+                format!("Failed to create cache directory: {}", parent.display())
+            })?;
+        }
+        let file = CacheFile {
+            version: CACHE_VERSION,
+            secret_patterns: crate::secrets::patterns_fingerprint(),
+            formats: crate::format::settings_fingerprint(),
+            files: std::mem::take(&mut state.files),
+        };
+        let data = serde_json::to_vec(&file);
+        state.files = file.files;
+        fs::write(path, data?)
+            .with_context(|| format!("Failed to write cache: {}", path.display()))?;
+        state.dirty = false;
+        Ok(())
+    }
+
+    fn parsed(&self, p: &ConfigFile) -> Result<CachedParse> {
+        if let Some(parsed) = self.entry(p)?.parsed {
+            return Ok(parsed);
+        }
+        let parsed = match p.parse() {
+            Ok(keys) => CachedParse::Keys {
+                canonical_hash: hash_flat_map(&keys),
+                keys,
+                source_map: p.source_map(),
+            },
+            Err(e) => CachedParse::Error(e.to_string()),
+        };
+        self.update(p, |e| e.parsed = Some(parsed.clone()));
+        Ok(parsed)
+    }
+
+    /// Current entry for the file, refreshed if the file changed since it was cached.
+    fn entry(&self, p: &ConfigFile) -> Result<CachedFile> {
+        let (size, mtime_ns) = match p {
+            ConfigFile::Disk(path) => {
+                let meta = fs::metadata(path)?;
+                let mtime_ns = meta
+                    .modified()
+                    .ok()
+                    .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                    .map(|d| d.as_nanos() as u64)
+                    .unwrap_or(0);
+                (meta.len(), mtime_ns)
+            }
+            ConfigFile::Member { content, .. } => (content.len() as u64, 0),
+        };
+        let key = cache_key(p);
+
+        if let ConfigFile::Disk(_) = p
+            && let Some(e) = self.lock().files.get(&key)
+            && e.size == size
+            && e.mtime_ns == mtime_ns
+        {
+            return Ok(e.clone());
+        }
+
+        // Hash outside the lock so other threads are not held up by file IO
+        let hash = p.hash()?;
+        let mut state = self.lock();
+        if let Some(e) = state.files.get(&key)
+            && e.hash == hash
+            && e.size == size
+            && e.mtime_ns == mtime_ns
+        {
+            return Ok(e.clone());
+        }
+        state.dirty = true;
+        let entry = match state.files.remove(&key) {
+            Some(old) if old.hash == hash => CachedFile {
+                size,
+                mtime_ns,
+                ..old
+            },
+            _ => CachedFile {
+                size,
+                mtime_ns,
+                hash,
+                parsed: None,
+                secrets: None,
+                violations: None,
+            },
+        };
+        state.files.insert(key, entry.clone());
+        Ok(entry)
+    }
+
+    fn update(&self, p: &ConfigFile, f: impl FnOnce(&mut CachedFile)) {
+        let mut state = self.lock();
+        if let Some(entry) = state.files.get_mut(&cache_key(p)) {
+            f(entry);
+            state.dirty = true;
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, CacheState> {
+        // A panic while holding the lock leaves plain data behind; keep using it
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// SHA-256 over any serializable value, used to fingerprint policies and pattern sets.
+pub fn fingerprint<T: Serialize>(value: &T) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(serde_json::to_vec(value).unwrap_or_default());
+    format!("{:x}", hasher.finalize())
+}
+
+/// Cache entries are keyed by absolute path, so a cache file shared between
+/// working directories neither misses nor mixes up files. Archive members keep
+/// their path inside the archive.
+fn cache_key(p: &ConfigFile) -> String {
+    let display = p.display();
+    let (file, member) = match display.split_once(ARCHIVE_SEPARATOR) {
+        Some((archive, inner)) => (archive, Some(inner)),
+        None => (display.as_str(), None),
+    };
+    let file = fs::canonicalize(file)
+        .map(|p| p.display().to_string())
+        .unwrap_or_else(|_| file.to_string());
+    match member {
+        Some(inner) => format!("{}{}{}", file, ARCHIVE_SEPARATOR, inner),
+        None => file,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::FlatValue;
+    use crate::utils::hash_file;
+
+    #[test]
+    fn test_cache_persists_and_reuses_results() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.yaml");
+        fs::write(&path, "app:\n  port: 8080\n").unwrap();
+        let file = ConfigFile::Disk(path.clone());
+        let cache_path = dir.path().join("cache.json");
+        let cache_str = cache_path.to_str().unwrap();
+
+        let cache = Cache::open(Some(cache_str)).unwrap();
+        let keys = cache.parse(&file).unwrap();
+        assert_eq!(keys.get("app.port"), Some(&FlatValue::Int(8080)));
+        assert!(cache.secrets(&file, || Ok(Vec::new())).unwrap().is_empty());
+        cache.save().unwrap();
+
+        let cache = Cache::open(Some(cache_str)).unwrap();
+        let findings = cache
+            .secrets(&file, || panic!("findings should come from the cache"))
+            .unwrap();
+        assert!(findings.is_empty());
+        assert_eq!(cache.hash(&file).unwrap(), hash_file(&path).unwrap());
+        let source_map = cache.source_map(&file).unwrap();
+        assert_eq!(
+            source_map
+                .span(&"app.port".parse().unwrap())
+                .map(|s| (s.line, s.column)),
+            Some((2, 3))
+        );
+    }
+
+    #[test]
+    fn test_entries_are_keyed_by_absolute_path() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("sub")).unwrap();
+        let path = dir.path().join("app.yaml");
+        fs::write(&path, "port: 8080\n").unwrap();
+        let cache = Cache::open(None).unwrap();
+        cache
+            .secrets(&ConfigFile::Disk(path), || Ok(Vec::new()))
+            .unwrap();
+
+        // The same file reached another way, as from another working directory
+        let other = ConfigFile::Disk(dir.path().join("sub/../app.yaml"));
+        let findings = cache
+            .secrets(&other, || panic!("findings should come from the cache"))
+            .unwrap();
+        assert!(findings.is_empty());
+        assert_eq!(cache.lock().files.len(), 1);
+    }
+
+    #[test]
+    fn test_changed_file_is_reprocessed() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.yaml");
+        fs::write(&path, "port: 8080\n").unwrap();
+
+        let file = ConfigFile::Disk(path.clone());
+        let cache = Cache::open(None).unwrap();
+        assert_eq!(cache.parse(&file).unwrap()["port"], FlatValue::Int(8080));
+        cache.secrets(&file, || Ok(Vec::new())).unwrap();
+
+        fs::write(&path, "port: 9090999\n").unwrap();
+        assert_eq!(cache.parse(&file).unwrap()["port"], FlatValue::Int(9090999));
+        let mut called = false;
+        cache
+            .secrets(&file, || {
+                called = true;
+                Ok(Vec::new())
+            })
+            .unwrap();
+        assert!(called);
+    }
+
+    #[test]
+    fn test_violations_invalidated_by_policy_change() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.yaml");
+        fs::write(&path, "debug: true\n").unwrap();
+
+        let file = ConfigFile::Disk(path);
+        let cache = Cache::open(None).unwrap();
+        cache.violations(&file, "policy-a", Vec::new).unwrap();
+        cache
+            .violations(&file, "policy-a", || panic!("should be cached"))
+            .unwrap();
+
+        let mut called = false;
+        cache
+            .violations(&file, "policy-b", || {
+                called = true;
+                Vec::new()
+            })
+            .unwrap();
+        assert!(called);
+    }
+}
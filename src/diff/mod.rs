@@ -0,0 +1,392 @@
+pub(crate) mod models;
+pub(crate) mod output;
+
+use anyhow::{Context, Result};
+use ed25519_dalek::VerifyingKey;
+use std::collections::BTreeMap;
+use std::fs;
+
+use crate::git::differ::diff_config_maps;
+use crate::git::models::KeyChange;
+use crate::models::{Entry, FileMetadata, FlatValue, Snapshot};
+use crate::policy::parser::FlatMap;
+use crate::signing;
+use crate::utils::format_mode;
+use models::{FileDiff, MetadataChange, SnapshotDiffReport};
+
+/// Compare two snapshot files and output the differences.
+/// Returns true if any file was added, removed or changed.
+pub fn diff(
+    old: &str,
+    new: &str,
+    format: &str,
+    output_file: Option<&str>,
+    verify_key: Option<&str>,
+    ignore_cosmetic: bool,
+) -> Result<bool> {
+    let trusted_key = verify_key.map(signing::load_verifying_key).transpose()?;
+    let o = load_snapshot(old, trusted_key.as_ref())?;
+    let n = load_snapshot(new, trusted_key.as_ref())?;
+
+    let mut report = compare_snapshots(&o, &n, old, new);
+    if ignore_cosmetic {
+        report = report.without_cosmetic();
+    }
+    let has_changes = report.has_changes();
+
+    match format {
+        "json" => output::output_json(&report, output_file)?,
+        "markdown" | "md" => {
+            output::output_markdown(&report, "ConfigTrace Snapshot Diff", output_file)?
+        }
+        _ => output::output_terminal(&report)?,
+    }
+
+    Ok(has_changes)
+}
+
+/// Read and deserialize a snapshot file.
+/// With a trusted key, unsigned snapshots or ones signed by another key are refused.
+pub(crate) fn load_snapshot(path: &str, trusted_key: Option<&VerifyingKey>) -> Result<Snapshot> {
+    let data = fs::read(path).with_context(|| format!("Failed to read snapshot: {}", path))?;
+    let snapshot: Snapshot = serde_json::from_slice(&data)
+        .with_context(|| format!("Failed to parse snapshot: {}", path))?;
+    if let Some(key) = trusted_key {
+        signing::verify_snapshot(&snapshot, key)
+            .with_context(|| format!("Snapshot failed verification: {}", path))?;
+    }
+    Ok(snapshot)
+}
+
+/// Build a structured diff between two snapshots.
+/// File lists are sorted by path for deterministic output.
+pub(crate) fn compare_snapshots(
+    old: &Snapshot,
+    new: &Snapshot,
+    old_name: &str,
+    new_name: &str,
+) -> SnapshotDiffReport {
+    let map_old: BTreeMap<_, _> = old.entries.iter().map(|e| (&e.path, e)).collect();
+    let map_new: BTreeMap<_, _> = new.entries.iter().map(|e| (&e.path, e)).collect();
+    let empty = BTreeMap::new();
+
+    let mut added = Vec::new();
+    let mut changed = Vec::new();
+    let mut removed = Vec::new();
+    let mut metadata_changed = Vec::new();
+
+    for (path, e_new) in &map_new {
+        if let Some(e_old) = map_old.get(path) {
+            let changes = metadata_changes(e_old.metadata.as_ref(), e_new.metadata.as_ref());
+            if !changes.is_empty() {
+                let mut diff = file_diff(Some(e_old), Some(e_new), Vec::new());
+                diff.cosmetic = false;
+                diff.metadata_changes = changes;
+                metadata_changed.push(diff);
+            }
+        }
+        match map_old.get(path) {
+            None => added.push(file_diff(
+                None,
+                Some(e_new),
+                key_changes(Some(&empty), e_new.keys.as_ref()),
+            )),
+            Some(e_old) if e_old.hash != e_new.hash => changed.push(file_diff(
+                Some(e_old),
+                Some(e_new),
+                key_changes(e_old.keys.as_ref(), e_new.keys.as_ref()),
+            )),
+            _ => {}
+        }
+    }
+    for (path, e_old) in &map_old {
+        if !map_new.contains_key(path) {
+            removed.push(file_diff(
+                Some(e_old),
+                None,
+                key_changes(e_old.keys.as_ref(), Some(&empty)),
+            ));
+        }
+    }
+
+    let total_cosmetic = changed.iter().filter(|f| f.cosmetic).count();
+
+    SnapshotDiffReport {
+        old_snapshot: old_name.to_string(),
+        new_snapshot: new_name.to_string(),
+        old_created_at: old.created_at.clone(),
+        new_created_at: new.created_at.clone(),
+        old_git_commit: old.git_commit.clone(),
+        new_git_commit: new.git_commit.clone(),
+        total_added: added.len(),
+        total_removed: removed.len(),
+        total_changed: changed.len(),
+        total_cosmetic,
+        total_metadata_changed: metadata_changed.len(),
+        added,
+        removed,
+        changed,
+        metadata_changed,
+    }
+}
+
+fn file_diff(old: Option<&Entry>, new: Option<&Entry>, key_changes: Vec<KeyChange>) -> FileDiff {
+    FileDiff {
+        path: new.or(old).map(|e| e.path.clone()).unwrap_or_default(),
+        old_hash: old.map(|e| e.hash.clone()),
+        new_hash: new.map(|e| e.hash.clone()),
+        cosmetic: is_cosmetic(old, new),
+        key_changes,
+        metadata_changes: Vec::new(),
+    }
+}
+
+/// Mode, ownership and symlink differences between two metadata records.
+/// Size and mtime are left out: they follow content changes, which the hash already covers.
+fn metadata_changes(old: Option<&FileMetadata>, new: Option<&FileMetadata>) -> Vec<MetadataChange> {
+    let (Some(old), Some(new)) = (old, new) else {
+        return Vec::new();
+    };
+    let mut changes = Vec::new();
+    let mut push = |field: &str, old_value: Option<String>, new_value: Option<String>| {
+        if old_value != new_value {
+            changes.push(MetadataChange {
+                field: field.to_string(),
+                old_value,
+                new_value,
+            });
+        }
+    };
+
+    // Mode and ownership are only compared when both sides recorded them (Unix)
+    if old.mode.is_some() && new.mode.is_some() {
+        push("mode", old.mode.map(format_mode), new.mode.map(format_mode));
+    }
+    if old.uid.is_some() && new.uid.is_some() {
+        push(
+            "uid",
+            old.uid.map(|v| v.to_string()),
+            new.uid.map(|v| v.to_string()),
+        );
+    }
+    if old.gid.is_some() && new.gid.is_some() {
+        push(
+            "gid",
+            old.gid.map(|v| v.to_string()),
+            new.gid.map(|v| v.to_string()),
+        );
+    }
+    push(
+        "symlink_target",
+        old.symlink_target.clone(),
+        new.symlink_target.clone(),
+    );
+    changes
+}
+
+/// A change is cosmetic when both sides parsed to the same canonical content.
+fn is_cosmetic(old: Option<&Entry>, new: Option<&Entry>) -> bool {
+    match (old, new) {
+        (Some(o), Some(n)) => {
+            o.hash != n.hash && o.canonical_hash.is_some() && o.canonical_hash == n.canonical_hash
+        }
+        _ => false,
+    }
+}
+
+/// Key-level changes between two embedded key maps.
+/// Empty when either side was captured without keys.
+fn key_changes(
+    old: Option<&BTreeMap<String, FlatValue>>,
+    new: Option<&BTreeMap<String, FlatValue>>,
+) -> Vec<KeyChange> {
+    match (old, new) {
+        (Some(old), Some(new)) => diff_config_maps(&to_hash_map(old), &to_hash_map(new)),
+        _ => Vec::new(),
+    }
+}
+
+fn to_hash_map(map: &BTreeMap<String, FlatValue>) -> FlatMap {
+    map.iter().map(|(k, v)| (k.clone(), v.clone())).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::models::KeyChangeKind;
+
+    fn entry(path: &str, hash: &str, keys: Option<&[(&str, &str)]>) -> Entry {
+        Entry {
+            path: path.to_string(),
+            hash: hash.to_string(),
+            canonical_hash: None,
+            keys: keys.map(|pairs| {
+                pairs
+                    .iter()
+                    .map(|(k, v)| (k.to_string(), FlatValue::from(*v)))
+                    .collect()
+            }),
+            metadata: None,
+        }
+    }
+
+    fn snapshot(created_at: &str, entries: Vec<Entry>) -> Snapshot {
+        Snapshot {
+            created_at: created_at.to_string(),
+            entries,
+            git_commit: None,
+            previous_hash: None,
+            signature: None,
+        }
+    }
+
+    #[test]
+    fn test_identical_snapshots() {
+        let old = snapshot("t1", vec![entry("a.yaml", "h1", None)]);
+        let new = snapshot("t2", vec![entry("a.yaml", "h1", None)]);
+        let report = compare_snapshots(&old, &new, "old.json", "new.json");
+        assert!(!report.has_changes());
+        assert_eq!(report.old_created_at, "t1");
+        assert_eq!(report.new_created_at, "t2");
+    }
+
+    #[test]
+    fn test_added_removed_changed() {
+        let old = snapshot(
+            "t1",
+            vec![entry("a.yaml", "h1", None), entry("b.yaml", "h2", None)],
+        );
+        let new = snapshot(
+            "t2",
+            vec![entry("a.yaml", "h1-new", None), entry("c.yaml", "h3", None)],
+        );
+        let report = compare_snapshots(&old, &new, "old.json", "new.json");
+
+        assert_eq!(report.total_added, 1);
+        assert_eq!(report.added[0].path, "c.yaml");
+        assert_eq!(report.added[0].old_hash, None);
+        assert_eq!(report.added[0].new_hash.as_deref(), Some("h3"));
+
+        assert_eq!(report.total_removed, 1);
+        assert_eq!(report.removed[0].path, "b.yaml");
+        assert_eq!(report.removed[0].new_hash, None);
+
+        assert_eq!(report.total_changed, 1);
+        assert_eq!(report.changed[0].old_hash.as_deref(), Some("h1"));
+        assert_eq!(report.changed[0].new_hash.as_deref(), Some("h1-new"));
+        assert!(report.changed[0].key_changes.is_empty());
+    }
+
+    #[test]
+    fn test_key_changes_when_both_sides_embed_keys() {
+        let old = snapshot(
+            "t1",
+            vec![entry(
+                "a.yaml",
+                "h1",
+                Some(&[("database.host", "localhost")]),
+            )],
+        );
+        let new = snapshot(
+            "t2",
+            vec![entry(
+                "a.yaml",
+                "h2",
+                Some(&[("database.host", "db.internal")]),
+            )],
+        );
+        let report = compare_snapshots(&old, &new, "old.json", "new.json");
+        let changes = &report.changed[0].key_changes;
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].kind, KeyChangeKind::Changed);
+        assert_eq!(changes[0].new_value, Some(FlatValue::from("db.internal")));
+    }
+
+    #[test]
+    fn test_cosmetic_change_detected() {
+        let mut old_entry = entry("a.yaml", "h1", None);
+        old_entry.canonical_hash = Some("c1".to_string());
+        let mut new_entry = entry("a.yaml", "h2", None);
+        new_entry.canonical_hash = Some("c1".to_string());
+        let mut semantic = entry("b.yaml", "h3", None);
+        semantic.canonical_hash = Some("c2".to_string());
+        let mut semantic_new = entry("b.yaml", "h4", None);
+        semantic_new.canonical_hash = Some("c3".to_string());
+
+        let old = snapshot("t1", vec![old_entry, semantic]);
+        let new = snapshot("t2", vec![new_entry, semantic_new]);
+        let report = compare_snapshots(&old, &new, "old.json", "new.json");
+        assert_eq!(report.total_changed, 2);
+        assert_eq!(report.total_cosmetic, 1);
+        assert!(report.changed[0].cosmetic);
+        assert!(!report.changed[1].cosmetic);
+
+        let report = report.without_cosmetic();
+        assert_eq!(report.total_changed, 1);
+        assert_eq!(report.changed[0].path, "b.yaml");
+    }
+
+    #[test]
+    fn test_missing_canonical_hash_is_not_cosmetic() {
+        let old = snapshot("t1", vec![entry("a.yaml", "h1", None)]);
+        let new = snapshot("t2", vec![entry("a.yaml", "h2", None)]);
+        let report = compare_snapshots(&old, &new, "old.json", "new.json");
+        assert!(!report.changed[0].cosmetic);
+    }
+
+    fn metadata(mode: u32, symlink_target: Option<&str>) -> FileMetadata {
+        FileMetadata {
+            mode: Some(mode),
+            uid: Some(0),
+            gid: Some(0),
+            size: 10,
+            mtime: Some("2025-01-15T00:00:00+00:00".to_string()),
+            symlink_target: symlink_target.map(String::from),
+        }
+    }
+
+    #[test]
+    fn test_metadata_drift_reported_separately() {
+        let mut old_entry = entry("db.yaml", "h1", None);
+        old_entry.metadata = Some(metadata(0o600, None));
+        let mut new_entry = entry("db.yaml", "h1", None);
+        let mut new_meta = metadata(0o644, Some("/etc/shared/db.yaml"));
+        new_meta.mtime = Some("2025-02-01T00:00:00+00:00".to_string());
+        new_entry.metadata = Some(new_meta);
+
+        let old = snapshot("t1", vec![old_entry]);
+        let new = snapshot("t2", vec![new_entry]);
+        let report = compare_snapshots(&old, &new, "old.json", "new.json");
+
+        assert!(report.has_changes());
+        assert_eq!(report.total_changed, 0);
+        assert_eq!(report.total_metadata_changed, 1);
+        let changes = &report.metadata_changed[0].metadata_changes;
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].field, "mode");
+        assert_eq!(changes[0].old_value.as_deref(), Some("0600"));
+        assert_eq!(changes[0].new_value.as_deref(), Some("0644"));
+        assert_eq!(changes[1].field, "symlink_target");
+    }
+
+    #[test]
+    fn test_no_metadata_drift_without_metadata() {
+        let mut old_entry = entry("db.yaml", "h1", None);
+        old_entry.metadata = Some(metadata(0o600, None));
+        let old = snapshot("t1", vec![old_entry]);
+        let new = snapshot("t2", vec![entry("db.yaml", "h1", None)]);
+        let report = compare_snapshots(&old, &new, "old.json", "new.json");
+        assert!(!report.has_changes());
+    }
+
+    #[test]
+    fn test_no_key_changes_when_one_side_lacks_keys() {
+        let old = snapshot("t1", vec![entry("a.yaml", "h1", None)]);
+        let new = snapshot(
+            "t2",
+            vec![entry("a.yaml", "h2", Some(&[("debug", "true")]))],
+        );
+        let report = compare_snapshots(&old, &new, "old.json", "new.json");
+        assert!(report.changed[0].key_changes.is_empty());
+    }
+}
@@ -0,0 +1,67 @@
+use serde::Serialize;
+
+use crate::git::models::KeyChange;
+
+/// A single file that differs between two snapshots.
+#[derive(Debug, Clone, Serialize)]
+pub struct FileDiff {
+    pub path: String,
+    pub old_hash: Option<String>,
+    pub new_hash: Option<String>,
+    /// True when the raw bytes changed but the canonical (parsed) content did not,
+    /// e.g. reordered keys, reindented YAML or edited comments.
+    pub cosmetic: bool,
+    /// Key-level changes, available when both snapshots embed key maps.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub key_changes: Vec<KeyChange>,
+    /// Permission, ownership or symlink changes, when both snapshots recorded metadata.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub metadata_changes: Vec<MetadataChange>,
+}
+
+/// A single metadata field that differs, e.g. `mode: 0600 -> 0644`.
+#[derive(Debug, Clone, Serialize)]
+pub struct MetadataChange {
+    pub field: String,
+    pub old_value: Option<String>,
+    pub new_value: Option<String>,
+}
+
+/// Top-level report for `configtrace diff`.
+#[derive(Debug, Serialize)]
+pub struct SnapshotDiffReport {
+    pub old_snapshot: String,
+    pub new_snapshot: String,
+    pub old_created_at: String,
+    pub new_created_at: String,
+    /// Source commits, for snapshots taken with `scan --ref`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub old_git_commit: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new_git_commit: Option<String>,
+    pub total_added: usize,
+    pub total_removed: usize,
+    pub total_changed: usize,
+    /// How many of the changed files only changed cosmetically.
+    pub total_cosmetic: usize,
+    /// Files whose mode, owner or symlink target changed, whatever their content did.
+    pub total_metadata_changed: usize,
+    pub added: Vec<FileDiff>,
+    pub removed: Vec<FileDiff>,
+    pub changed: Vec<FileDiff>,
+    pub metadata_changed: Vec<FileDiff>,
+}
+
+impl SnapshotDiffReport {
+    pub fn has_changes(&self) -> bool {
+        self.total_added + self.total_removed + self.total_changed + self.total_metadata_changed > 0
+    }
+
+    /// Drop files whose changes are purely cosmetic, keeping only semantic drift.
+    pub fn without_cosmetic(mut self) -> Self {
+        self.changed.retain(|f| !f.cosmetic);
+        self.total_changed = self.changed.len();
+        self.total_cosmetic = 0;
+        self
+    }
+}
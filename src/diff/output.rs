@@ -0,0 +1,322 @@
+use anyhow::Result;
+use std::io::Write;
+use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
+
+use super::models::{FileDiff, MetadataChange, SnapshotDiffReport};
+use crate::git::models::KeyChangeKind;
+use crate::git::output::write_key_change;
+
+// ===== Terminal Output =====
+
+pub fn output_terminal(report: &SnapshotDiffReport) -> Result<()> {
+    let mut stdout = StandardStream::stdout(ColorChoice::Auto);
+
+    writeln!(&mut stdout)?;
+    stdout.set_color(ColorSpec::new().set_bold(true))?;
+    writeln!(
+        &mut stdout,
+        "Snapshot diff: {} .. {}",
+        report.old_snapshot, report.new_snapshot
+    )?;
+    stdout.reset()?;
+    writeln!(
+        &mut stdout,
+        "  Taken: {} .. {}",
+        report.old_created_at, report.new_created_at
+    )?;
+    if report.old_git_commit.is_some() || report.new_git_commit.is_some() {
+        writeln!(
+            &mut stdout,
+            "  Commit: {} .. {}",
+            report.old_git_commit.as_deref().unwrap_or("-"),
+            report.new_git_commit.as_deref().unwrap_or("-")
+        )?;
+    }
+    writeln!(&mut stdout)?;
+
+    if !report.has_changes() {
+        writeln!(&mut stdout, "  No changes found.")?;
+        writeln!(&mut stdout)?;
+        return Ok(());
+    }
+
+    for file in &report.added {
+        write_file_diff(&mut stdout, "ADDED  ", Color::Green, file)?;
+    }
+    for file in &report.changed {
+        write_file_diff(&mut stdout, "CHANGED", Color::Yellow, file)?;
+    }
+    for file in &report.removed {
+        write_file_diff(&mut stdout, "REMOVED", Color::Red, file)?;
+    }
+    for file in &report.metadata_changed {
+        write_file_diff(&mut stdout, "PERMS  ", Color::Magenta, file)?;
+    }
+
+    // Summary
+    writeln!(&mut stdout)?;
+    writeln!(&mut stdout, "━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━")?;
+    stdout.set_color(ColorSpec::new().set_bold(true))?;
+    writeln!(&mut stdout, "Summary:")?;
+    stdout.reset()?;
+    writeln!(&mut stdout, "  Files added:   {}", report.total_added)?;
+    writeln!(&mut stdout, "  Files removed: {}", report.total_removed)?;
+    writeln!(
+        &mut stdout,
+        "  Files changed: {}{}",
+        report.total_changed,
+        cosmetic_suffix(report)
+    )?;
+    writeln!(
+        &mut stdout,
+        "  Metadata changed: {}",
+        report.total_metadata_changed
+    )?;
+    writeln!(&mut stdout)?;
+
+    Ok(())
+}
+
+/// Terminal output for `configtrace verify`, where the new side is a live directory.
+pub fn output_verify_terminal(report: &SnapshotDiffReport) -> Result<()> {
+    let mut stdout = StandardStream::stdout(ColorChoice::Auto);
+
+    writeln!(&mut stdout)?;
+    stdout.set_color(ColorSpec::new().set_bold(true))?;
+    writeln!(
+        &mut stdout,
+        "Verifying {} against {}",
+        report.new_snapshot, report.old_snapshot
+    )?;
+    stdout.reset()?;
+    writeln!(&mut stdout, "  Baseline taken: {}", report.old_created_at)?;
+    if let Some(commit) = &report.old_git_commit {
+        writeln!(&mut stdout, "  Baseline commit: {}", commit)?;
+    }
+    writeln!(&mut stdout)?;
+
+    if !report.has_changes() {
+        stdout.set_color(ColorSpec::new().set_fg(Some(Color::Green)).set_bold(true))?;
+        writeln!(&mut stdout, "No drift detected.")?;
+        stdout.reset()?;
+        writeln!(&mut stdout)?;
+        return Ok(());
+    }
+
+    for file in &report.added {
+        write_file_diff(&mut stdout, "NEW     ", Color::Green, file)?;
+    }
+    for file in &report.removed {
+        write_file_diff(&mut stdout, "MISSING ", Color::Red, file)?;
+    }
+    for file in &report.changed {
+        write_file_diff(&mut stdout, "MODIFIED", Color::Yellow, file)?;
+    }
+    for file in &report.metadata_changed {
+        write_file_diff(&mut stdout, "PERMS   ", Color::Magenta, file)?;
+    }
+
+    // Summary
+    writeln!(&mut stdout)?;
+    writeln!(&mut stdout, "━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━")?;
+    stdout.set_color(ColorSpec::new().set_bold(true))?;
+    writeln!(&mut stdout, "Summary:")?;
+    stdout.reset()?;
+    writeln!(
+        &mut stdout,
+        "  Unexpected new files: {}",
+        report.total_added
+    )?;
+    writeln!(
+        &mut stdout,
+        "  Missing files:        {}",
+        report.total_removed
+    )?;
+    writeln!(
+        &mut stdout,
+        "  Modified files:       {}{}",
+        report.total_changed,
+        cosmetic_suffix(report)
+    )?;
+    writeln!(
+        &mut stdout,
+        "  Metadata changed:     {}",
+        report.total_metadata_changed
+    )?;
+    writeln!(&mut stdout)?;
+
+    stdout.set_color(ColorSpec::new().set_fg(Some(Color::Red)).set_bold(true))?;
+    writeln!(&mut stdout, "Drift detected!")?;
+    stdout.reset()?;
+
+    Ok(())
+}
+
+fn write_file_diff(
+    stdout: &mut StandardStream,
+    label: &str,
+    color: Color,
+    file: &FileDiff,
+) -> Result<()> {
+    stdout.set_color(ColorSpec::new().set_fg(Some(color)))?;
+    write!(stdout, "{}", label)?;
+    stdout.reset()?;
+    write!(stdout, " {}", file.path)?;
+    if file.cosmetic {
+        stdout.set_color(ColorSpec::new().set_dimmed(true))?;
+        write!(stdout, " (formatting only)")?;
+        stdout.reset()?;
+    }
+    writeln!(stdout)?;
+    for change in &file.key_changes {
+        write!(stdout, "  ")?;
+        write_key_change(stdout, change)?;
+        writeln!(stdout)?;
+    }
+    for change in &file.metadata_changes {
+        writeln!(stdout, "  ~ {}", format_metadata_change(change))?;
+    }
+    Ok(())
+}
+
+fn format_metadata_change(change: &MetadataChange) -> String {
+    format!(
+        "{}: {} -> {}",
+        change.field,
+        change.old_value.as_deref().unwrap_or("(none)"),
+        change.new_value.as_deref().unwrap_or("(none)")
+    )
+}
+
+// ===== JSON Output =====
+
+pub fn output_json(report: &SnapshotDiffReport, output_file: Option<&str>) -> Result<()> {
+    let json = serde_json::to_string_pretty(report)?;
+    if let Some(path) = output_file {
+        std::fs::write(path, &json)?;
+        println!("Wrote snapshot diff to {}", path);
+    } else {
+        println!("{}", json);
+    }
+    Ok(())
+}
+
+// ===== Markdown Output =====
+
+pub fn output_markdown(
+    report: &SnapshotDiffReport,
+    title: &str,
+    output_file: Option<&str>,
+) -> Result<()> {
+    let mut md = String::new();
+
+    md.push_str(&format!("# {}\n\n", title));
+    md.push_str(&format!(
+        "**Old:** `{}` ({}) | **New:** `{}` ({})\n\n",
+        report.old_snapshot, report.old_created_at, report.new_snapshot, report.new_created_at,
+    ));
+
+    md.push_str("| Change | Files |\n");
+    md.push_str("|--------|-------|\n");
+    md.push_str(&format!("| Added | {} |\n", report.total_added));
+    md.push_str(&format!("| Removed | {} |\n", report.total_removed));
+    md.push_str(&format!(
+        "| Changed | {}{} |\n",
+        report.total_changed,
+        cosmetic_suffix(report)
+    ));
+    md.push_str(&format!(
+        "| Metadata changed | {} |\n\n",
+        report.total_metadata_changed
+    ));
+
+    if !report.has_changes() {
+        md.push_str("No changes found.\n");
+    }
+
+    let sections = [
+        ("Added", &report.added),
+        ("Removed", &report.removed),
+        ("Changed", &report.changed),
+    ];
+    for (title, files) in sections {
+        if files.is_empty() {
+            continue;
+        }
+        md.push_str(&format!("## {}\n\n", title));
+        md.push_str("| File | Old SHA256 | New SHA256 |\n");
+        md.push_str("|------|------------|------------|\n");
+        for file in files {
+            md.push_str(&format!(
+                "| {}{} | {} | {} |\n",
+                file.path,
+                if file.cosmetic {
+                    " (formatting only)"
+                } else {
+                    ""
+                },
+                short_hash(file.old_hash.as_deref()),
+                short_hash(file.new_hash.as_deref())
+            ));
+        }
+        md.push('\n');
+
+        for file in files.iter().filter(|f| !f.key_changes.is_empty()) {
+            md.push_str(&format!("### `{}`\n\n", file.path));
+            for change in &file.key_changes {
+                let (old_value, new_value) = change.display_values();
+                let line = match change.kind {
+                    KeyChangeKind::Added => format!("- `+ {}` = `{}`\n", change.key, new_value),
+                    KeyChangeKind::Removed => format!("- `- {}` = `{}`\n", change.key, old_value),
+                    KeyChangeKind::Changed => {
+                        format!("- `~ {}`: `{}` -> `{}`\n", change.key, old_value, new_value)
+                    }
+                };
+                md.push_str(&line);
+            }
+            md.push('\n');
+        }
+    }
+
+    if !report.metadata_changed.is_empty() {
+        md.push_str("## Metadata Changed\n\n");
+        md.push_str("| File | Field | Old | New |\n");
+        md.push_str("|------|-------|-----|-----|\n");
+        for file in &report.metadata_changed {
+            for change in &file.metadata_changes {
+                md.push_str(&format!(
+                    "| {} | {} | `{}` | `{}` |\n",
+                    file.path,
+                    change.field,
+                    change.old_value.as_deref().unwrap_or("-"),
+                    change.new_value.as_deref().unwrap_or("-")
+                ));
+            }
+        }
+        md.push('\n');
+    }
+
+    if let Some(path) = output_file {
+        std::fs::write(path, &md)?;
+        println!("Wrote snapshot diff to {}", path);
+    } else {
+        print!("{}", md);
+    }
+
+    Ok(())
+}
+
+fn cosmetic_suffix(report: &SnapshotDiffReport) -> String {
+    if report.total_cosmetic > 0 {
+        format!(" ({} formatting only)", report.total_cosmetic)
+    } else {
+        String::new()
+    }
+}
+
+fn short_hash(hash: Option<&str>) -> String {
+    match hash {
+        Some(h) => format!("`{}`", &h[..12.min(h.len())]),
+        None => "-".to_string(),
+    }
+}
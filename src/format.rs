@@ -0,0 +1,366 @@
+use anyhow::{Context, Result, anyhow};
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path};
+use std::sync::OnceLock;
+
+/// Settings file read from the working directory when `--config` is not given.
+pub const SETTINGS_FILE: &str = ".configtrace.yaml";
+
+/// Files larger than this are never sniffed.
+pub const MAX_SNIFF_BYTES: u64 = 1 << 20;
+
+/// Suffixes of templates and examples, dropped to find the real extension:
+/// `values.yaml.j2` is YAML and `config.json.example` is JSON.
+const TEMPLATE_SUFFIXES: &[&str] = &[
+    "tpl", "tmpl", "template", "j2", "jinja", "jinja2", "example", "sample", "dist",
+];
+
+static REGISTRY: OnceLock<FormatRegistry> = OnceLock::new();
+
+lazy_static! {
+    static ref DOTENV_LINE: Regex = Regex::new(r"^(export\s+)?[A-Za-z_][A-Za-z0-9_.]*=").unwrap();
+}
+
+/// A supported config file format, detected from the file name or, failing that, its content.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConfigFormat {
+    Yaml,
+    Json,
+    /// JSON5, which also covers JSONC (JSON with comments and trailing commas).
+    Json5,
+    Toml,
+    Dotenv,
+    Ini,
+    Properties,
+    Hcl,
+    Xml,
+}
+
+impl ConfigFormat {
+    /// Detect the format of a file from its name, or None if it is not a config file.
+    ///
+    /// Globs from the `formats` settings are tried first, in order. Otherwise the
+    /// extension decides, after dropping a template suffix (`.tpl`, `.j2`, `.example`, ...).
+    /// Dotenv files are matched by name: `.env`, `.env.<anything>` (e.g. `.env.production`)
+    /// and `<anything>.env`. A known extension wins, so `.env.local.json` is JSON.
+    pub fn from_path(p: &Path) -> Option<ConfigFormat> {
+        registry().format_of(p)
+    }
+
+    /// Detect the format of a file from its name, falling back to its content for files
+    /// without an extension (`Caddyfile`-style names) that are not dotfiles, see `sniff`.
+    pub fn detect(p: &Path, content: &[u8]) -> Option<ConfigFormat> {
+        let registry = registry();
+        registry
+            .format_of(p)
+            .or_else(|| registry.sniffable(p).then(|| sniff(content)).flatten())
+    }
+
+    /// Whether `detect` may look at the content of a file `from_path` does not recognise:
+    /// it has no extension, is not a dotfile, is not inside `.git` and sniffing is enabled.
+    /// Lets callers skip reading files that could never match.
+    pub fn sniffable(p: &Path) -> bool {
+        registry().sniffable(p)
+    }
+
+    /// Short lowercase name used in reports.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ConfigFormat::Yaml => "yaml",
+            ConfigFormat::Json => "json",
+            ConfigFormat::Json5 => "json5",
+            ConfigFormat::Toml => "toml",
+            ConfigFormat::Dotenv => "dotenv",
+            ConfigFormat::Ini => "ini",
+            ConfigFormat::Properties => "properties",
+            ConfigFormat::Hcl => "hcl",
+            ConfigFormat::Xml => "xml",
+        }
+    }
+}
+
+/// Format from the extension or dotenv name, ignoring a template suffix.
+fn from_name(p: &Path) -> Option<ConfigFormat> {
+    let name = p.file_name()?.to_str()?;
+    let name = match name.rsplit_once('.') {
+        Some((stem, suffix)) if !stem.is_empty() && TEMPLATE_SUFFIXES.contains(&suffix) => stem,
+        _ => name,
+    };
+    match Path::new(name).extension().and_then(|s| s.to_str()) {
+        Some("yml" | "yaml") => return Some(ConfigFormat::Yaml),
+        Some("json") => return Some(ConfigFormat::Json),
+        Some("jsonc" | "json5") => return Some(ConfigFormat::Json5),
+        Some("toml") => return Some(ConfigFormat::Toml),
+        Some("env") => return Some(ConfigFormat::Dotenv),
+        Some("ini" | "cfg") => return Some(ConfigFormat::Ini),
+        Some("properties") => return Some(ConfigFormat::Properties),
+        Some("hcl" | "tf" | "tfvars") => return Some(ConfigFormat::Hcl),
+        Some("xml" | "config") => return Some(ConfigFormat::Xml),
+        _ => {}
+    }
+    (name == ".env" || name.starts_with(".env.")).then_some(ConfigFormat::Dotenv)
+}
+
+/// Guess the format of a file from its content, or None if it does not look like config.
+///
+/// Only small UTF-8 text is considered, and scripts starting with a `#!` line never
+/// match. XML starts with `<`, JSON must parse, dotenv
+/// has only `KEY=value` lines, and TOML or YAML must parse to a non-empty table. INI,
+/// properties and HCL are too loose to tell apart from plain text, so they need a glob.
+pub fn sniff(content: &[u8]) -> Option<ConfigFormat> {
+    if content.len() as u64 > MAX_SNIFF_BYTES || content.contains(&0) {
+        return None;
+    }
+    let text = std::str::from_utf8(content).ok()?;
+    if text.starts_with("#!") {
+        return None;
+    }
+    let trimmed = text.trim();
+    if trimmed.starts_with('<') {
+        return trimmed.ends_with('>').then_some(ConfigFormat::Xml);
+    }
+    if trimmed.starts_with(['{', '[']) {
+        if serde_json::from_str::<serde_json::Value>(text).is_ok() {
+            return Some(ConfigFormat::Json);
+        }
+        if json5::from_str::<serde_json::Value>(text).is_ok() {
+            return Some(ConfigFormat::Json5);
+        }
+    }
+    let mut lines = text
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .peekable();
+    if lines.peek().is_some() && lines.all(|l| DOTENV_LINE.is_match(l)) {
+        return Some(ConfigFormat::Dotenv);
+    }
+    if toml::from_str::<toml::Table>(text).is_ok_and(|t| !t.is_empty()) {
+        return Some(ConfigFormat::Toml);
+    }
+    match serde_yaml::from_str(text) {
+        Ok(serde_yaml::Value::Mapping(map)) if !map.is_empty() => Some(ConfigFormat::Yaml),
+        _ => None,
+    }
+}
+
+/// The `.configtrace.yaml` settings that control format detection.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FormatSettings {
+    /// Globs mapped to formats, tried in order before extensions.
+    #[serde(default)]
+    pub formats: Vec<FormatMapping>,
+    /// Detect the format of files without an extension from their content.
+    #[serde(default = "default_sniff")]
+    pub sniff: bool,
+}
+
+impl Default for FormatSettings {
+    fn default() -> Self {
+        FormatSettings {
+            formats: Vec::new(),
+            sniff: true,
+        }
+    }
+}
+
+fn default_sniff() -> bool {
+    true
+}
+
+/// Files matching `glob` are parsed as `format`. A glob containing `/` or `**`
+/// matches the whole path, otherwise just the file name (`Caddyfile`, `*.yaml.gotmpl`).
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FormatMapping {
+    pub glob: String,
+    pub format: ConfigFormat,
+}
+
+/// Format detection rules, built once from the settings.
+#[derive(Default)]
+struct FormatRegistry {
+    settings: FormatSettings,
+    globs: Vec<(glob::Pattern, ConfigFormat)>,
+}
+
+impl FormatRegistry {
+    fn new(settings: FormatSettings) -> Result<FormatRegistry> {
+        let globs = settings
+            .formats
+            .iter()
+            .map(|m| {
+                glob::Pattern::new(&m.glob)
+                    .map(|p| (p, m.format))
+                    .with_context(|| format!("Invalid format glob: {}", m.glob))
+            })
+            .collect::<Result<_>>()?;
+        Ok(FormatRegistry { settings, globs })
+    }
+
+    fn format_of(&self, p: &Path) -> Option<ConfigFormat> {
+        let name = p.file_name().map(|n| n.to_string_lossy());
+        self.globs
+            .iter()
+            .find(|(glob, _)| {
+                if glob.as_str().contains('/') || glob.as_str().contains("**") {
+                    glob.matches_path(p)
+                } else {
+                    name.as_deref().is_some_and(|n| glob.matches(n))
+                }
+            })
+            .map(|(_, format)| *format)
+            .or_else(|| from_name(p))
+    }
+
+    fn sniffable(&self, p: &Path) -> bool {
+        self.settings.sniff
+            && p.extension().is_none()
+            && !p
+                .file_name()
+                .is_some_and(|n| n.to_string_lossy().starts_with('.'))
+            && !p
+                .components()
+                .any(|c| c == Component::Normal(".git".as_ref()))
+    }
+}
+
+/// Load format settings from `path`, or from `.configtrace.yaml` in the working
+/// directory when it exists. Without either, only built-in extensions and sniffing apply.
+pub fn init(path: Option<&str>) -> Result<()> {
+    let path = path.or(Path::new(SETTINGS_FILE).is_file().then_some(SETTINGS_FILE));
+    let registry = match path {
+        Some(p) => {
+            let content = std::fs::read_to_string(p)
+                .with_context(|| format!("Failed to read settings: {}", p))?;
+            let settings = serde_yaml::from_str(&content)
+                .with_context(|| format!("Failed to parse settings: {}", p))?;
+            FormatRegistry::new(settings)?
+        }
+        None => FormatRegistry::default(),
+    };
+    REGISTRY
+        .set(registry)
+        .map_err(|_| anyhow!("Format settings are already loaded"))
+}
+
+fn registry() -> &'static FormatRegistry {
+    REGISTRY.get_or_init(FormatRegistry::default)
+}
+
+/// Fingerprint of the format settings, so cached parses are dropped when they change.
+pub(crate) fn settings_fingerprint() -> String {
+    crate::cache::fingerprint(&registry().settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format_of(name: &str) -> Option<ConfigFormat> {
+        ConfigFormat::from_path(Path::new(name))
+    }
+
+    #[test]
+    fn test_from_path() {
+        assert_eq!(format_of("app/config.yml"), Some(ConfigFormat::Yaml));
+        assert_eq!(format_of("Cargo.toml"), Some(ConfigFormat::Toml));
+        assert_eq!(
+            format_of(".vscode/settings.jsonc"),
+            Some(ConfigFormat::Json5)
+        );
+        assert_eq!(format_of("app.json5"), Some(ConfigFormat::Json5));
+        assert_eq!(format_of(".env"), Some(ConfigFormat::Dotenv));
+        assert_eq!(format_of("app/.env.production"), Some(ConfigFormat::Dotenv));
+        assert_eq!(format_of("prod.env"), Some(ConfigFormat::Dotenv));
+        assert_eq!(format_of(".env.local.json"), Some(ConfigFormat::Json));
+        assert_eq!(format_of("setup.cfg"), Some(ConfigFormat::Ini));
+        assert_eq!(format_of("etc/nginx/nginx.conf"), None);
+        assert_eq!(
+            format_of("application.properties"),
+            Some(ConfigFormat::Properties)
+        );
+        assert_eq!(format_of("prod.tfvars"), Some(ConfigFormat::Hcl));
+        assert_eq!(format_of("main.tf"), Some(ConfigFormat::Hcl));
+        assert_eq!(format_of("conf/server.xml"), Some(ConfigFormat::Xml));
+        assert_eq!(format_of("web.config"), Some(ConfigFormat::Xml));
+        assert_eq!(format_of(".envrc"), None);
+        assert_eq!(format_of("README.md"), None);
+    }
+
+    #[test]
+    fn test_template_suffixes() {
+        assert_eq!(format_of("charts/values.yaml.j2"), Some(ConfigFormat::Yaml));
+        assert_eq!(format_of("config.yaml.tpl"), Some(ConfigFormat::Yaml));
+        assert_eq!(format_of("config.json.example"), Some(ConfigFormat::Json));
+        assert_eq!(format_of(".env.example"), Some(ConfigFormat::Dotenv));
+        assert_eq!(format_of("README.md.tmpl"), None);
+        assert_eq!(format_of("notes.sample"), None);
+    }
+
+    #[test]
+    fn test_registry_globs() {
+        let settings: FormatSettings = serde_yaml::from_str(
+            "formats:\n  - glob: Caddyfile\n    format: ini\n  - glob: \"*.conf\"\n    format: ini\n  - glob: \"deploy/**/*.txt\"\n    format: yaml\n  - glob: \"*.json\"\n    format: json5\n",
+        )
+        .unwrap();
+        let registry = FormatRegistry::new(settings).unwrap();
+        let format_of = |name: &str| registry.format_of(Path::new(name));
+        assert_eq!(format_of("srv/Caddyfile"), Some(ConfigFormat::Ini));
+        assert_eq!(format_of("etc/app.conf"), Some(ConfigFormat::Ini));
+        assert_eq!(format_of("deploy/prod/app.txt"), Some(ConfigFormat::Yaml));
+        assert_eq!(format_of("docs/app.txt"), None);
+        assert_eq!(format_of("tsconfig.json"), Some(ConfigFormat::Json5));
+        assert_eq!(format_of("app.toml"), Some(ConfigFormat::Toml));
+
+        assert!(serde_yaml::from_str::<FormatSettings>("formats: []\nsnif: false\n").is_err());
+        let settings = FormatSettings {
+            formats: vec![FormatMapping {
+                glob: "[".to_string(),
+                format: ConfigFormat::Yaml,
+            }],
+            sniff: true,
+        };
+        assert!(FormatRegistry::new(settings).is_err());
+    }
+
+    #[test]
+    fn test_sniff() {
+        assert_eq!(
+            sniff(b"<?xml version=\"1.0\"?>\n<a/>\n"),
+            Some(ConfigFormat::Xml)
+        );
+        assert_eq!(sniff(b"{\"a\": 1}"), Some(ConfigFormat::Json));
+        assert_eq!(sniff(b"{a: 1, // note\n}"), Some(ConfigFormat::Json5));
+        assert_eq!(
+            sniff(b"# settings\nexport API_URL=https://x\nDEBUG=false\n"),
+            Some(ConfigFormat::Dotenv)
+        );
+        assert_eq!(sniff(b"[server]\nport = 8080\n"), Some(ConfigFormat::Toml));
+        assert_eq!(sniff(b"server:\n  port: 8080\n"), Some(ConfigFormat::Yaml));
+        assert_eq!(sniff(b"Just some notes.\n"), None);
+        assert_eq!(sniff(b"[section]\nkey=value with spaces\n"), None);
+        assert_eq!(sniff(b"a: 1\0"), None);
+        assert_eq!(sniff(b"#!/bin/sh\nFOO=bar\n"), None);
+        assert_eq!(sniff(b""), None);
+    }
+
+    #[test]
+    fn test_sniffable() {
+        let registry = FormatRegistry::default();
+        assert!(registry.sniffable(Path::new("srv/Caddyfile")));
+        assert!(!registry.sniffable(Path::new("notes.txt")));
+        assert!(!registry.sniffable(Path::new(".git/HEAD")));
+        assert!(!registry.sniffable(Path::new("home/.bashrc")));
+        let registry = FormatRegistry::new(FormatSettings {
+            formats: Vec::new(),
+            sniff: false,
+        })
+        .unwrap();
+        assert!(!registry.sniffable(Path::new("srv/Caddyfile")));
+    }
+}
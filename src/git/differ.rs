@@ -1,13 +1,16 @@
-use std::collections::{BTreeSet, HashMap};
+use std::collections::BTreeSet;
 
 use super::models::{KeyChange, KeyChangeKind};
+use crate::keypath::KeyPath;
+use crate::policy::parser::{FlatMap, SourceMap, key_by_resource};
 
 /// Compare two flattened config maps and produce key-level changes.
 /// Uses BTreeSet for deterministic key ordering in output.
-pub fn diff_config_maps(
-    old: &HashMap<String, String>,
-    new: &HashMap<String, String>,
-) -> Vec<KeyChange> {
+/// Kubernetes-style resources are matched by identity rather than position,
+/// so reordering or splitting manifests only reports real changes.
+pub fn diff_config_maps(old: &FlatMap, new: &FlatMap) -> Vec<KeyChange> {
+    let old = &key_by_resource(old);
+    let new = &key_by_resource(new);
     let mut changes = Vec::new();
     let all_keys: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
 
@@ -15,26 +18,32 @@ pub fn diff_config_maps(
         match (old.get(key), new.get(key)) {
             (None, Some(new_val)) => {
                 changes.push(KeyChange {
-                    key: key.clone(),
+                    key: KeyPath::from_flat(key),
                     kind: KeyChangeKind::Added,
                     old_value: None,
                     new_value: Some(new_val.clone()),
+                    span: None,
+                    origin: None,
                 });
             }
             (Some(old_val), None) => {
                 changes.push(KeyChange {
-                    key: key.clone(),
+                    key: KeyPath::from_flat(key),
                     kind: KeyChangeKind::Removed,
                     old_value: Some(old_val.clone()),
                     new_value: None,
+                    span: None,
+                    origin: None,
                 });
             }
             (Some(old_val), Some(new_val)) if old_val != new_val => {
                 changes.push(KeyChange {
-                    key: key.clone(),
+                    key: KeyPath::from_flat(key),
                     kind: KeyChangeKind::Changed,
                     old_value: Some(old_val.clone()),
                     new_value: Some(new_val.clone()),
+                    span: None,
+                    origin: None,
                 });
             }
             _ => {}
@@ -44,21 +53,36 @@ pub fn diff_config_maps(
     changes
 }
 
+/// Point each change at the line of its key: in the new content,
+/// or in the old one for removed keys. Values inherited from a YAML anchor
+/// also name the anchor.
+pub fn locate_changes(changes: &mut [KeyChange], old_map: &SourceMap, new_map: &SourceMap) {
+    for change in changes {
+        let source_map = match change.kind {
+            KeyChangeKind::Removed => old_map,
+            _ => new_map,
+        };
+        change.span = source_map.span(&change.key);
+        change.origin = source_map.origin(&change.key).cloned();
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::models::FlatValue;
 
-    fn make_map(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+    fn make_map(pairs: &[(&str, &str)]) -> FlatMap {
         pairs
             .iter()
-            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .map(|(k, v)| (k.to_string(), FlatValue::from(*v)))
             .collect()
     }
 
     #[test]
     fn test_empty_maps() {
-        let old = HashMap::new();
-        let new = HashMap::new();
+        let old = FlatMap::new();
+        let new = FlatMap::new();
         assert!(diff_config_maps(&old, &new).is_empty());
     }
 
@@ -70,25 +94,25 @@ mod tests {
 
     #[test]
     fn test_added_keys() {
-        let old = HashMap::new();
+        let old = FlatMap::new();
         let new = make_map(&[("database.host", "localhost")]);
         let changes = diff_config_maps(&old, &new);
         assert_eq!(changes.len(), 1);
         assert_eq!(changes[0].kind, KeyChangeKind::Added);
         assert_eq!(changes[0].key, "database.host");
-        assert_eq!(changes[0].new_value.as_deref(), Some("localhost"));
+        assert_eq!(changes[0].new_value, Some(FlatValue::from("localhost")));
         assert!(changes[0].old_value.is_none());
     }
 
     #[test]
     fn test_removed_keys() {
         let old = make_map(&[("debug", "true")]);
-        let new = HashMap::new();
+        let new = FlatMap::new();
         let changes = diff_config_maps(&old, &new);
         assert_eq!(changes.len(), 1);
         assert_eq!(changes[0].kind, KeyChangeKind::Removed);
         assert_eq!(changes[0].key, "debug");
-        assert_eq!(changes[0].old_value.as_deref(), Some("true"));
+        assert_eq!(changes[0].old_value, Some(FlatValue::from("true")));
         assert!(changes[0].new_value.is_none());
     }
 
@@ -99,8 +123,11 @@ mod tests {
         let changes = diff_config_maps(&old, &new);
         assert_eq!(changes.len(), 1);
         assert_eq!(changes[0].kind, KeyChangeKind::Changed);
-        assert_eq!(changes[0].old_value.as_deref(), Some("localhost"));
-        assert_eq!(changes[0].new_value.as_deref(), Some("db.prod.internal"));
+        assert_eq!(changes[0].old_value, Some(FlatValue::from("localhost")));
+        assert_eq!(
+            changes[0].new_value,
+            Some(FlatValue::from("db.prod.internal"))
+        );
     }
 
     #[test]
@@ -133,6 +160,38 @@ mod tests {
         assert_eq!(added[0].key, "d");
     }
 
+    #[test]
+    fn test_type_change_is_reported() {
+        let old = make_map(&[("port", "5432"), ("debug", "false")]);
+        let mut new = old.clone();
+        new.insert("port".to_string(), FlatValue::Int(5432));
+        let changes = diff_config_maps(&old, &new);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].key, "port");
+        assert_eq!(
+            changes[0].display_values(),
+            ("\"5432\"".to_string(), "5432".to_string())
+        );
+    }
+
+    #[test]
+    fn test_non_finite_and_unsigned_values_compare_by_value() {
+        let mut old = FlatMap::new();
+        old.insert("ratio".to_string(), FlatValue::Float(f64::NAN));
+        old.insert("limit".to_string(), FlatValue::UInt(u64::MAX));
+        assert!(diff_config_maps(&old, &old.clone()).is_empty());
+
+        let mut new = old.clone();
+        new.insert("ratio".to_string(), FlatValue::from(".nan"));
+        new.insert("limit".to_string(), FlatValue::Float(u64::MAX as f64));
+        let changes = diff_config_maps(&old, &new);
+        assert_eq!(changes.len(), 2);
+        assert_eq!(
+            changes[1].display_values(),
+            (".nan".to_string(), "\".nan\"".to_string())
+        );
+    }
+
     #[test]
     fn test_deterministic_ordering() {
         let old = make_map(&[("z", "1"), ("a", "2")]);
@@ -141,4 +200,63 @@ mod tests {
         assert_eq!(changes[0].key, "a");
         assert_eq!(changes[1].key, "z");
     }
+
+    #[test]
+    fn test_resources_matched_by_identity() {
+        use crate::policy::parser::parse_config_content;
+        use std::path::Path;
+
+        let deployment =
+            "apiVersion: apps/v1\nkind: Deployment\nmetadata:\n  name: api\nspec:\n  replicas: 2\n";
+        let service = "apiVersion: v1\nkind: Service\nmetadata:\n  name: api\n";
+        let old = parse_config_content(deployment, Path::new("app.yaml")).unwrap();
+        let new = parse_config_content(
+            &format!("{}---\n{}", service, deployment.replace("2", "3")),
+            Path::new("app.yaml"),
+        )
+        .unwrap();
+
+        let changes = diff_config_maps(&old, &new);
+        let changed: Vec<_> = changes
+            .iter()
+            .filter(|c| c.kind == KeyChangeKind::Changed)
+            .map(|c| c.key.to_string())
+            .collect();
+        assert_eq!(changed, vec!["[apps/v1/Deployment/api].spec.replicas"]);
+        assert!(
+            changes
+                .iter()
+                .filter(|c| c.kind == KeyChangeKind::Added)
+                .all(|c| c.key.to_string().starts_with("[v1/Service/api]"))
+        );
+        assert!(!changes.iter().any(|c| c.kind == KeyChangeKind::Removed));
+    }
+
+    #[test]
+    fn test_changes_located_in_old_and_new_content() {
+        use crate::policy::parser::parse_config_source_map;
+        use std::path::Path;
+
+        let old_content = "server:\n  port: 80\n  debug: true\n";
+        let new_content = "server:\n  host: web\n  port: 8080\n";
+        let old_spans = parse_config_source_map(old_content, Path::new("app.yaml"));
+        let new_spans = parse_config_source_map(new_content, Path::new("app.yaml"));
+        let old = make_map(&[("server.port", "80"), ("server.debug", "true")]);
+        let new = make_map(&[("server.host", "web"), ("server.port", "8080")]);
+
+        let mut changes = diff_config_maps(&old, &new);
+        locate_changes(&mut changes, &old_spans, &new_spans);
+        let lines: Vec<_> = changes
+            .iter()
+            .map(|c| (c.key.to_string(), c.span.map(|s| s.line)))
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                ("server.debug".to_string(), Some(3)),
+                ("server.host".to_string(), Some(2)),
+                ("server.port".to_string(), Some(3)),
+            ]
+        );
+    }
 }
@@ -1,13 +1,14 @@
-mod differ;
+pub(crate) mod differ;
 pub(crate) mod models;
-mod output;
-mod repo;
+pub(crate) mod output;
+pub(crate) mod repo;
 
 use anyhow::{Context, Result};
 use std::collections::{BTreeSet, HashMap};
 use std::path::Path;
 
 use crate::policy;
+use crate::policy::parser::{FlatMap, SourceMap};
 
 /// Collect config change history data without outputting.
 /// Used by the report module for unified audit reports.
@@ -33,11 +34,6 @@ pub fn collect_log(path_filter: Option<&str>, limit: usize) -> Result<models::Gi
         let mut file_changes_list = Vec::new();
 
         for file_path in &config_files {
-            let ext = Path::new(file_path)
-                .extension()
-                .and_then(|s| s.to_str())
-                .unwrap_or("");
-
             let new_content = repo::get_file_content_at_commit(&repository, &commit, file_path)?;
             let old_content = match &parent {
                 Some(p) => repo::get_file_content_at_commit(&repository, p, file_path)?,
@@ -46,13 +42,13 @@ pub fn collect_log(path_filter: Option<&str>, limit: usize) -> Result<models::Gi
 
             let new_map = new_content
                 .as_ref()
-                .and_then(|c| policy::parser::parse_config_content(c, ext).ok());
+                .and_then(|c| policy::parser::parse_config_content(c, Path::new(file_path)).ok());
             let old_map = old_content
                 .as_ref()
-                .and_then(|c| policy::parser::parse_config_content(c, ext).ok());
+                .and_then(|c| policy::parser::parse_config_content(c, Path::new(file_path)).ok());
 
             let empty = HashMap::new();
-            let changes = differ::diff_config_maps(
+            let mut changes = differ::diff_config_maps(
                 old_map.as_ref().unwrap_or(&empty),
                 new_map.as_ref().unwrap_or(&empty),
             );
@@ -60,6 +56,12 @@ pub fn collect_log(path_filter: Option<&str>, limit: usize) -> Result<models::Gi
             if changes.is_empty() {
                 continue;
             }
+            let new_source_map = content_source_map(new_content.as_deref(), file_path);
+            differ::locate_changes(
+                &mut changes,
+                &content_source_map(old_content.as_deref(), file_path),
+                &new_source_map,
+            );
 
             file_changes_list.push(build_file_changes(file_path, changes, Vec::new()));
         }
@@ -69,16 +71,18 @@ pub fn collect_log(path_filter: Option<&str>, limit: usize) -> Result<models::Gi
             if config_files.contains(file_path) {
                 continue;
             }
-            let ext = Path::new(file_path)
-                .extension()
-                .and_then(|s| s.to_str())
-                .unwrap_or("");
 
             if let Some(p) = &parent
                 && let Some(content) = repo::get_file_content_at_commit(&repository, p, file_path)?
-                && let Ok(old_map) = policy::parser::parse_config_content(&content, ext)
+                && let Ok(old_map) =
+                    policy::parser::parse_config_content(&content, Path::new(file_path))
             {
-                let changes = differ::diff_config_maps(&old_map, &HashMap::new());
+                let mut changes = differ::diff_config_maps(&old_map, &HashMap::new());
+                differ::locate_changes(
+                    &mut changes,
+                    &content_source_map(Some(&content), file_path),
+                    &SourceMap::default(),
+                );
                 if !changes.is_empty() {
                     file_changes_list.push(build_file_changes(file_path, changes, Vec::new()));
                 }
@@ -148,11 +152,6 @@ pub fn git_log(
 
         // Check files in current commit
         for file_path in &config_files {
-            let ext = Path::new(file_path)
-                .extension()
-                .and_then(|s| s.to_str())
-                .unwrap_or("");
-
             let new_content = repo::get_file_content_at_commit(&repository, &commit, file_path)?;
             let old_content = match &parent {
                 Some(p) => repo::get_file_content_at_commit(&repository, p, file_path)?,
@@ -161,13 +160,13 @@ pub fn git_log(
 
             let new_map = new_content
                 .as_ref()
-                .and_then(|c| policy::parser::parse_config_content(c, ext).ok());
+                .and_then(|c| policy::parser::parse_config_content(c, Path::new(file_path)).ok());
             let old_map = old_content
                 .as_ref()
-                .and_then(|c| policy::parser::parse_config_content(c, ext).ok());
+                .and_then(|c| policy::parser::parse_config_content(c, Path::new(file_path)).ok());
 
             let empty = HashMap::new();
-            let changes = differ::diff_config_maps(
+            let mut changes = differ::diff_config_maps(
                 old_map.as_ref().unwrap_or(&empty),
                 new_map.as_ref().unwrap_or(&empty),
             );
@@ -175,9 +174,16 @@ pub fn git_log(
             if changes.is_empty() {
                 continue;
             }
+            let new_source_map = content_source_map(new_content.as_deref(), file_path);
+            differ::locate_changes(
+                &mut changes,
+                &content_source_map(old_content.as_deref(), file_path),
+                &new_source_map,
+            );
 
             // Policy evaluation on the current version
-            let violations = evaluate_policy_on_map(&policy_file, new_map.as_ref(), file_path);
+            let violations =
+                evaluate_policy_on_map(&policy_file, new_map.as_ref(), &new_source_map, file_path);
             if !violations.is_empty() {
                 has_violations = true;
             }
@@ -190,16 +196,18 @@ pub fn git_log(
             if config_files.contains(file_path) {
                 continue;
             }
-            let ext = Path::new(file_path)
-                .extension()
-                .and_then(|s| s.to_str())
-                .unwrap_or("");
 
             if let Some(p) = &parent
                 && let Some(content) = repo::get_file_content_at_commit(&repository, p, file_path)?
-                && let Ok(old_map) = policy::parser::parse_config_content(&content, ext)
+                && let Ok(old_map) =
+                    policy::parser::parse_config_content(&content, Path::new(file_path))
             {
-                let changes = differ::diff_config_maps(&old_map, &HashMap::new());
+                let mut changes = differ::diff_config_maps(&old_map, &HashMap::new());
+                differ::locate_changes(
+                    &mut changes,
+                    &content_source_map(Some(&content), file_path),
+                    &SourceMap::default(),
+                );
                 if !changes.is_empty() {
                     file_changes_list.push(build_file_changes(file_path, changes, Vec::new()));
                 }
@@ -272,30 +280,31 @@ pub fn git_diff(
     let mut total_changed = 0;
 
     for file_path in &all_files {
-        let ext = Path::new(file_path)
-            .extension()
-            .and_then(|s| s.to_str())
-            .unwrap_or("");
-
         let content1 = repo::get_file_content_at_commit(&repository, &commit1, file_path)?;
         let content2 = repo::get_file_content_at_commit(&repository, &commit2, file_path)?;
 
         let map1 = content1
             .as_ref()
-            .and_then(|c| policy::parser::parse_config_content(c, ext).ok())
+            .and_then(|c| policy::parser::parse_config_content(c, Path::new(file_path)).ok())
             .unwrap_or_default();
         let map2 = content2
             .as_ref()
-            .and_then(|c| policy::parser::parse_config_content(c, ext).ok())
+            .and_then(|c| policy::parser::parse_config_content(c, Path::new(file_path)).ok())
             .unwrap_or_default();
 
-        let changes = differ::diff_config_maps(&map1, &map2);
+        let mut changes = differ::diff_config_maps(&map1, &map2);
         if changes.is_empty() {
             continue;
         }
+        let source_map2 = content_source_map(content2.as_deref(), file_path);
+        differ::locate_changes(
+            &mut changes,
+            &content_source_map(content1.as_deref(), file_path),
+            &source_map2,
+        );
 
         // Policy evaluation on the target ref (ref2)
-        let violations = evaluate_policy_on_map(&policy_file, Some(&map2), file_path);
+        let violations = evaluate_policy_on_map(&policy_file, Some(&map2), &source_map2, file_path);
         if !violations.is_empty() {
             has_violations = true;
         }
@@ -345,9 +354,17 @@ fn load_optional_policy(
     }
 }
 
+/// Line and column of each key in a file's content at one revision.
+fn content_source_map(content: Option<&str>, file_path: &str) -> SourceMap {
+    content
+        .map(|c| policy::parser::parse_config_source_map(c, Path::new(file_path)))
+        .unwrap_or_default()
+}
+
 fn evaluate_policy_on_map(
     policy_file: &Option<crate::policy::models::PolicyFile>,
-    config_map: Option<&HashMap<String, String>>,
+    config_map: Option<&FlatMap>,
+    source_map: &SourceMap,
     file_path: &str,
 ) -> Vec<crate::policy::models::Violation> {
     let Some(pf) = policy_file else {
@@ -357,15 +374,25 @@ fn evaluate_policy_on_map(
         return Vec::new();
     };
 
+    let documents = policy::parser::split_documents(map, source_map);
     let mut violations = Vec::new();
     for rule in &pf.rules {
         if !policy::evaluator::rule_applies_to_file(rule, Path::new(file_path)) {
             continue;
         }
-        if let Some(v) = policy::evaluator::evaluate_rule(rule, Path::new(file_path), map) {
-            violations.push(v);
-        }
+        violations.extend(policy::evaluator::evaluate_rule_on_documents(
+            rule,
+            Path::new(file_path),
+            map,
+            &documents,
+            source_map,
+        ));
     }
+    violations.extend(policy::evaluator::duplicate_key_violations(
+        pf,
+        Path::new(file_path),
+        source_map,
+    ));
     violations
 }
 
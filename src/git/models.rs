@@ -1,5 +1,7 @@
 use serde::Serialize;
 
+use crate::keypath::KeyPath;
+use crate::models::{FlatValue, Origin, Span};
 use crate::policy::models::Violation;
 
 /// How a config key changed between two states.
@@ -14,10 +16,38 @@ pub enum KeyChangeKind {
 /// A single key-level change within a config file.
 #[derive(Debug, Clone, Serialize)]
 pub struct KeyChange {
-    pub key: String,
+    pub key: KeyPath,
     pub kind: KeyChangeKind,
-    pub old_value: Option<String>,
-    pub new_value: Option<String>,
+    pub old_value: Option<FlatValue>,
+    pub new_value: Option<FlatValue>,
+    /// Where the key is written: in the new content, or in the old one for removed keys.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub span: Option<Span>,
+    /// Set when the value is inherited from a YAML anchor written elsewhere.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub origin: Option<Origin>,
+}
+
+impl KeyChange {
+    /// Old and new value as shown in diffs. When the type changed, strings are
+    /// quoted so the change is visible: `"5432" -> 5432`.
+    pub fn display_values(&self) -> (String, String) {
+        let type_changed = match (&self.old_value, &self.new_value) {
+            (Some(old), Some(new)) => old.value_type() != new.value_type(),
+            _ => false,
+        };
+        let render = |value: &Option<FlatValue>| match value {
+            Some(v @ FlatValue::String(_)) if type_changed => v.to_json().to_string(),
+            Some(v) => v.to_string(),
+            None => String::new(),
+        };
+        (render(&self.old_value), render(&self.new_value))
+    }
+
+    /// Where the change is, as shown next to it in terminal output.
+    pub fn location(&self) -> Option<String> {
+        crate::models::describe_location(self.span, self.origin.as_ref())
+    }
 }
 
 /// Changes to a single config file between two states.
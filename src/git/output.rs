@@ -6,7 +6,7 @@ use super::models::*;
 
 // ===== Shared helpers =====
 
-fn write_key_change(stdout: &mut StandardStream, change: &KeyChange) -> Result<()> {
+pub(crate) fn write_key_change(stdout: &mut StandardStream, change: &KeyChange) -> Result<()> {
     match change.kind {
         KeyChangeKind::Added => {
             stdout.set_color(ColorSpec::new().set_fg(Some(Color::Green)))?;
@@ -28,14 +28,13 @@ fn write_key_change(stdout: &mut StandardStream, change: &KeyChange) -> Result<(
             stdout.set_color(ColorSpec::new().set_fg(Some(Color::Yellow)))?;
             write!(stdout, "~ {}", change.key)?;
             stdout.reset()?;
-            write!(
-                stdout,
-                ": {} -> {}",
-                change.old_value.as_deref().unwrap_or(""),
-                change.new_value.as_deref().unwrap_or("")
-            )?;
+            let (old_value, new_value) = change.display_values();
+            write!(stdout, ": {} -> {}", old_value, new_value)?;
         }
     }
+    if let Some(location) = change.location() {
+        write!(stdout, " ({})", location)?;
+    }
     Ok(())
 }
 
@@ -46,7 +45,11 @@ fn write_violation(
     stdout.set_color(ColorSpec::new().set_fg(Some(Color::Red)).set_bold(true))?;
     write!(stdout, "VIOLATION")?;
     stdout.reset()?;
-    writeln!(stdout, " [{}] {}: {}", v.severity, v.rule_id, v.message)?;
+    write!(stdout, " [{}] {}: {}", v.severity, v.rule_id, v.message)?;
+    if let Some(location) = v.location() {
+        write!(stdout, " ({})", location)?;
+    }
+    writeln!(stdout)?;
     Ok(())
 }
 
@@ -2,7 +2,8 @@ use anyhow::{Context, Result};
 use git2::{Commit, ObjectType, Repository, Sort, Tree};
 use std::path::Path;
 
-use crate::utils::is_config;
+use crate::format::MAX_SNIFF_BYTES;
+use crate::utils::is_config_content;
 
 /// Open the git repository at or above the current directory.
 pub fn open_repo() -> Result<Repository> {
@@ -25,16 +26,29 @@ pub fn get_file_content_at_commit(
     commit: &Commit,
     file_path: &str,
 ) -> Result<Option<String>> {
+    match get_file_bytes_at_commit(repo, commit, file_path)? {
+        Some(bytes) => match String::from_utf8(bytes) {
+            Ok(content) => Ok(Some(content)),
+            Err(_) => Ok(None), // Binary file, skip
+        },
+        None => Ok(None),
+    }
+}
+
+/// Get the raw bytes of a file at a specific commit.
+/// Returns None if the file does not exist at that commit.
+pub fn get_file_bytes_at_commit(
+    repo: &Repository,
+    commit: &Commit,
+    file_path: &str,
+) -> Result<Option<Vec<u8>>> {
     let tree = commit.tree()?;
     match tree.get_path(Path::new(file_path)) {
         Ok(entry) => {
             let blob = repo
                 .find_blob(entry.id())
                 .with_context(|| format!("Failed to read blob for {}", file_path))?;
-            match std::str::from_utf8(blob.content()) {
-                Ok(content) => Ok(Some(content.to_string())),
-                Err(_) => Ok(None), // Binary file, skip
-            }
+            Ok(Some(blob.content().to_vec()))
         }
         Err(_) => Ok(None),
     }
@@ -57,7 +71,7 @@ pub fn list_config_files_in_tree(
             } else {
                 // It's a file, not a directory - check if it's a config file
                 let full_path = filter.to_string();
-                if is_config(Path::new(&full_path)) {
+                if is_config_content(Path::new(&full_path), || blob_content(repo, entry.id())) {
                     files.push(full_path);
                 }
             }
@@ -71,7 +85,7 @@ pub fn list_config_files_in_tree(
 }
 
 fn collect_config_files(
-    _repo: &Repository,
+    repo: &Repository,
     tree: &Tree,
     prefix: &str,
     files: &mut Vec<String>,
@@ -97,7 +111,7 @@ fn collect_config_files(
                 }
             };
 
-            if is_config(Path::new(&full_path)) {
+            if is_config_content(Path::new(&full_path), || blob_content(repo, entry.id())) {
                 files.push(full_path);
             }
         }
@@ -106,6 +120,12 @@ fn collect_config_files(
     Ok(())
 }
 
+/// Content of a blob small enough to sniff.
+fn blob_content(repo: &Repository, id: git2::Oid) -> Option<Vec<u8>> {
+    let blob = repo.find_blob(id).ok()?;
+    (blob.size() as u64 <= MAX_SNIFF_BYTES).then(|| blob.content().to_vec())
+}
+
 /// Walk commits starting from HEAD, limited to N commits.
 pub fn walk_commits(repo: &Repository, limit: usize) -> Result<Vec<git2::Oid>> {
     let mut revwalk = repo.revwalk()?;
@@ -0,0 +1,394 @@
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// One step of a key path.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Segment {
+    /// A map key, matched exactly.
+    Key(String),
+    /// A list position. Documents of a multi-document file without a resource
+    /// identity are numbered the same way.
+    Index(usize),
+    /// A document of a multi-document file, by resource identity (`apps/v1/Deployment/prod/api`).
+    Document(String),
+}
+
+/// Path of a flattened key, e.g. `database.hosts[0].name`.
+///
+/// Map keys are joined with dots and list positions are written in brackets. A key
+/// that contains `.`, `[` or `]`, is empty, or starts the path with `/` is quoted in
+/// brackets instead: `metadata.labels["app.kubernetes.io/name"]` is one label, while
+/// `a.b` is `b` nested in `a`. Inside quotes, `\"` and `\\` escape a quote and a backslash.
+/// A leading `[id]` names a document of a multi-document file.
+///
+/// A path starting with `/` is read as a JSON Pointer (`/metadata/labels/app.kubernetes.io~1name`).
+/// Its numeric tokens are list positions until `resolve` finds a map there instead, so
+/// `/codes/404` reaches `codes.404` as well as `codes[404]`. Paths are always shown in the
+/// dotted form, which is also how flattened keys are written.
+#[derive(Debug, Clone, Default)]
+pub struct KeyPath {
+    segments: Vec<Segment>,
+    /// Parsed from a JSON Pointer, so `Index` segments may still be map keys.
+    pointer: bool,
+}
+
+impl KeyPath {
+    /// Parse a dotted path or a JSON Pointer.
+    pub fn parse(path: &str) -> Result<KeyPath> {
+        let segments = match path.strip_prefix('/') {
+            Some(pointer) => parse_pointer(pointer),
+            None => parse_dotted(path).with_context(|| format!("Invalid key path: {}", path))?,
+        };
+        Ok(KeyPath {
+            segments,
+            pointer: path.starts_with('/'),
+        })
+    }
+
+    /// Path of a key read from a flat map. Keys written before paths were escaped
+    /// may not parse; they are kept as a single key.
+    pub fn from_flat(key: &str) -> KeyPath {
+        KeyPath::parse(key).unwrap_or_else(|_| KeyPath {
+            segments: vec![Segment::Key(key.to_string())],
+            pointer: false,
+        })
+    }
+
+    /// Path of a document of a multi-document file, by identity or position.
+    pub fn document(id: &str) -> KeyPath {
+        let segment = match id.parse() {
+            Ok(index) => Segment::Index(index),
+            Err(_) => Segment::Document(id.to_string()),
+        };
+        KeyPath {
+            segments: vec![segment],
+            pointer: false,
+        }
+    }
+
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    /// Whether the path starts with a document (`[apps/v1/Deployment/prod/api].spec`)
+    /// or a position (`[0].name`), and so addresses a whole multi-document file.
+    pub fn names_document(&self) -> bool {
+        matches!(
+            self.segments.first(),
+            Some(Segment::Index(_) | Segment::Document(_))
+        )
+    }
+
+    /// This path followed by `other`.
+    pub fn join(&self, other: &KeyPath) -> KeyPath {
+        let mut segments = self.segments.clone();
+        segments.extend(other.segments.iter().cloned());
+        KeyPath {
+            segments,
+            pointer: self.pointer || other.pointer,
+        }
+    }
+
+    /// The path without its last segment, or None for the empty path.
+    pub fn parent(&self) -> Option<KeyPath> {
+        let (_, parent) = self.segments.split_last()?;
+        Some(KeyPath {
+            segments: parent.to_vec(),
+            pointer: self.pointer,
+        })
+    }
+
+    /// The path without a leading document or position, or None if it has none.
+    pub fn without_document(&self) -> Option<KeyPath> {
+        self.names_document().then(|| KeyPath {
+            segments: self.segments[1..].to_vec(),
+            pointer: self.pointer,
+        })
+    }
+
+    /// The path with each numeric JSON Pointer token read against the container it
+    /// indexes: a list position where `exists` finds one, otherwise a map key if
+    /// `exists` finds that. Dotted paths are returned as they are.
+    pub fn resolve(&self, exists: impl Fn(&str) -> bool) -> KeyPath {
+        if !self.pointer {
+            return self.clone();
+        }
+        let mut rendered = String::new();
+        let mut segments = Vec::with_capacity(self.segments.len());
+        for segment in &self.segments {
+            let segment = match segment {
+                Segment::Index(index)
+                    if !exists(&join_index(&rendered, *index))
+                        && exists(&join_key(&rendered, &index.to_string())) =>
+                {
+                    Segment::Key(index.to_string())
+                }
+                _ => segment.clone(),
+            };
+            rendered = join_segment(&rendered, &segment);
+            segments.push(segment);
+        }
+        KeyPath {
+            segments,
+            pointer: false,
+        }
+    }
+}
+
+/// Paths are equal when they address the same keys, however they were written.
+impl PartialEq for KeyPath {
+    fn eq(&self, other: &KeyPath) -> bool {
+        self.segments == other.segments
+    }
+}
+
+impl Eq for KeyPath {}
+
+impl std::hash::Hash for KeyPath {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.segments.hash(state);
+    }
+}
+
+impl std::fmt::Display for KeyPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut rendered = String::new();
+        for segment in &self.segments {
+            rendered = join_segment(&rendered, segment);
+        }
+        f.write_str(&rendered)
+    }
+}
+
+impl std::str::FromStr for KeyPath {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<KeyPath> {
+        KeyPath::parse(s)
+    }
+}
+
+/// Compares with the dotted form, so `path == "a.b[0]"` reads naturally.
+impl PartialEq<&str> for KeyPath {
+    fn eq(&self, other: &&str) -> bool {
+        self.to_string().as_str() == *other
+    }
+}
+
+impl Serialize for KeyPath {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for KeyPath {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let path = String::deserialize(deserializer)?;
+        KeyPath::parse(&path).map_err(|e| serde::de::Error::custom(format!("{:#}", e)))
+    }
+}
+
+/// Append a map key to a flattened key, quoting it when it would be ambiguous.
+pub fn join_key(prefix: &str, key: &str) -> String {
+    let first = prefix.is_empty();
+    if key.is_empty() || key.contains(['.', '[', ']']) || (first && key.starts_with('/')) {
+        let quoted = key.replace('\\', "\\\\").replace('"', "\\\"");
+        format!("{}[\"{}\"]", prefix, quoted)
+    } else if first {
+        key.to_string()
+    } else {
+        format!("{}.{}", prefix, key)
+    }
+}
+
+/// Append a list position to a flattened key.
+pub fn join_index(prefix: &str, index: usize) -> String {
+    format!("{}[{}]", prefix, index)
+}
+
+fn join_segment(prefix: &str, segment: &Segment) -> String {
+    match segment {
+        Segment::Key(key) => join_key(prefix, key),
+        Segment::Index(index) => join_index(prefix, *index),
+        Segment::Document(id) => format!("{}[{}]", prefix, id),
+    }
+}
+
+/// Split a flattened key into its leading document or position (`[id]`) and the
+/// rest, or None when it does not start with one.
+pub fn split_document(key: &str) -> Option<(&str, &str)> {
+    let inner = key.strip_prefix('[').filter(|k| !k.starts_with('"'))?;
+    let end = inner.find(']')?;
+    let rest = &inner[end + 1..];
+    Some((&inner[..end], rest.strip_prefix('.').unwrap_or(rest)))
+}
+
+fn parse_dotted(path: &str) -> Result<Vec<Segment>> {
+    let mut segments = Vec::new();
+    let mut chars = path.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c == '[' {
+            chars.next();
+            segments.push(bracketed(&mut chars)?);
+        } else {
+            if !segments.is_empty() {
+                if c != '.' {
+                    bail!("expected `.` or `[` after `]`");
+                }
+                chars.next();
+            }
+            let mut key = String::new();
+            while let Some(&c) = chars.peek() {
+                match c {
+                    '.' | '[' => break,
+                    ']' => bail!("unexpected `]`"),
+                    _ => key.push(c),
+                }
+                chars.next();
+            }
+            if key.is_empty() {
+                bail!("empty key, write it as [\"\"]");
+            }
+            segments.push(Segment::Key(key));
+        }
+    }
+    Ok(segments)
+}
+
+/// A `[...]` segment, after the opening bracket.
+fn bracketed(chars: &mut Peekable<Chars>) -> Result<Segment> {
+    if chars.next_if_eq(&'"').is_some() {
+        let mut key = String::new();
+        loop {
+            match chars.next() {
+                Some('\\') => key.push(chars.next().context("unterminated quoted key")?),
+                Some('"') => break,
+                Some(c) => key.push(c),
+                None => bail!("unterminated quoted key"),
+            }
+        }
+        if chars.next() != Some(']') {
+            bail!("expected `]` after quoted key");
+        }
+        return Ok(Segment::Key(key));
+    }
+    let mut inner = String::new();
+    loop {
+        match chars.next() {
+            Some(']') => break,
+            Some(c) => inner.push(c),
+            None => bail!("unterminated `[`"),
+        }
+    }
+    Ok(match inner.parse() {
+        Ok(index) => Segment::Index(index),
+        Err(_) if !inner.is_empty() => Segment::Document(inner),
+        Err(_) => bail!("empty `[]`"),
+    })
+}
+
+/// Segments of a JSON Pointer, without its leading `/`.
+fn parse_pointer(pointer: &str) -> Vec<Segment> {
+    pointer
+        .split('/')
+        .map(|token| {
+            let key = token.replace("~1", "/").replace("~0", "~");
+            let is_index = key == "0" || (!key.starts_with('0') && !key.is_empty());
+            match key.parse() {
+                Ok(index) if is_index => Segment::Index(index),
+                _ => Segment::Key(key),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(s: &str) -> KeyPath {
+        KeyPath::parse(s).unwrap()
+    }
+
+    #[test]
+    fn test_parse_and_render() {
+        let label = path(r#"metadata.labels["app.kubernetes.io/name"]"#);
+        assert_eq!(
+            label.segments(),
+            &[
+                Segment::Key("metadata".to_string()),
+                Segment::Key("labels".to_string()),
+                Segment::Key("app.kubernetes.io/name".to_string()),
+            ]
+        );
+        assert_eq!(label, r#"metadata.labels["app.kubernetes.io/name"]"#);
+        assert_ne!(path("a.b"), path(r#"["a.b"]"#));
+
+        let nested = path("[apps/v1/Deployment/prod/api].spec.containers[0].image");
+        assert!(nested.names_document());
+        assert_eq!(nested.segments()[3], Segment::Index(0));
+        assert_eq!(
+            nested.to_string(),
+            "[apps/v1/Deployment/prod/api].spec.containers[0].image"
+        );
+
+        let escaped = path(r#"a["say \"hi\" \\ bye"]["x]y"].b"#);
+        assert_eq!(
+            escaped.segments()[1],
+            Segment::Key(r#"say "hi" \ bye"#.to_string())
+        );
+        assert_eq!(path(&escaped.to_string()), escaped);
+        assert_eq!(path(r#"["/health"]"#).to_string(), r#"["/health"]"#);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for bad in ["a..b", "a.", ".a", "a[0", "a[]", "a]b", r#"a["b"#, "a[0]b"] {
+            assert!(KeyPath::parse(bad).is_err(), "{}", bad);
+        }
+        assert_eq!(KeyPath::from_flat("a]b").segments().len(), 1);
+    }
+
+    #[test]
+    fn test_json_pointer() {
+        assert_eq!(
+            path("/metadata/labels/app.kubernetes.io~1name"),
+            path(r#"metadata.labels["app.kubernetes.io/name"]"#)
+        );
+        assert_eq!(path("/servers/0/host"), "servers[0].host");
+        assert_eq!(path("/a~0b/007"), "a~b.007");
+    }
+
+    #[test]
+    fn test_json_pointer_resolves_numeric_map_keys() {
+        let keys = ["codes.404", "servers[0].host", "ports.8080[1]"];
+        let exists = |k: &str| {
+            keys.iter().any(|key| {
+                key.strip_prefix(k)
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with(['.', '[']))
+            })
+        };
+        assert_eq!(path("/codes/404").resolve(exists), "codes.404");
+        assert_eq!(path("/servers/0/host").resolve(exists), "servers[0].host");
+        assert_eq!(path("/ports/8080/1").resolve(exists), "ports.8080[1]");
+        // Nothing there either way: still a list position
+        assert_eq!(path("/codes/500").resolve(exists), "codes[500]");
+        // Dotted paths say which one they mean
+        assert_eq!(path("codes[404]").resolve(exists), "codes[404]");
+    }
+
+    #[test]
+    fn test_join_keys_match_rendering() {
+        let key = join_key(&join_index(&join_key("", "spec"), 2), "a.b");
+        assert_eq!(key, r#"spec[2]["a.b"]"#);
+        assert_eq!(path(&key).to_string(), key);
+        assert_eq!(join_key("", ""), r#"[""]"#);
+        assert_eq!(
+            split_document("[v1/Service/api].spec"),
+            Some(("v1/Service/api", "spec"))
+        );
+        assert_eq!(split_document(r#"["a.b"].c"#), None);
+    }
+}
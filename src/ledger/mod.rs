@@ -0,0 +1,144 @@
+pub(crate) mod models;
+mod output;
+mod store;
+
+use anyhow::{Context, Result};
+
+use crate::cache::Cache;
+use crate::diff;
+use crate::scan::collect_snapshot;
+use crate::signing;
+use crate::walk::WalkOptions;
+use store::Store;
+
+/// Scan a directory and append the snapshot to the ledger, linked to the previous one.
+pub fn record(
+    store_dir: &str,
+    path: &str,
+    with_keys: bool,
+    sign_key: Option<&str>,
+    walk: &WalkOptions,
+    cache: &Cache,
+) -> Result<()> {
+    let store = Store::create(store_dir)?;
+    let mut snapshot = collect_snapshot(path, with_keys, walk, cache)?;
+    cache.save()?;
+    snapshot.previous_hash = store.head_hash()?;
+    if let Some(key_path) = sign_key {
+        let key = signing::load_signing_key(key_path)?;
+        signing::sign_snapshot(&mut snapshot, &key)?;
+    }
+    let index = store.append(&snapshot)?;
+    println!(
+        "Recorded snapshot #{} ({} files) in {}",
+        index,
+        snapshot.entries.len(),
+        store.root().display()
+    );
+    Ok(())
+}
+
+/// List the snapshots in the ledger, oldest first.
+pub fn list(store_dir: &str, format: &str) -> Result<()> {
+    let store = Store::open(store_dir)?;
+    let entries = store.list()?;
+    match format {
+        "json" => output::output_list_json(&entries)?,
+        _ => output::output_list_terminal(store_dir, &entries)?,
+    }
+    Ok(())
+}
+
+/// Show a single snapshot by index or date.
+pub fn show(store_dir: &str, reference: &str, format: &str) -> Result<()> {
+    let store = Store::open(store_dir)?;
+    let index = store.resolve(reference)?;
+    let snapshot = store.load(index)?;
+    match format {
+        "json" => println!("{}", serde_json::to_string_pretty(&snapshot)?),
+        _ => output::output_show_terminal(index, &snapshot)?,
+    }
+    Ok(())
+}
+
+/// Diff two ledger snapshots by index or date.
+/// With `verify_key`, both snapshots must be signed by that key.
+/// Returns true if any file was added, removed or changed.
+pub fn diff(
+    store_dir: &str,
+    from: &str,
+    to: &str,
+    format: &str,
+    output_file: Option<&str>,
+    verify_key: Option<&str>,
+    ignore_cosmetic: bool,
+) -> Result<bool> {
+    let trusted_key = verify_key.map(signing::load_verifying_key).transpose()?;
+    let store = Store::open(store_dir)?;
+    let from_index = store.resolve(from)?;
+    let to_index = store.resolve(to)?;
+    let old = store.load(from_index)?;
+    let new = store.load(to_index)?;
+    if let Some(key) = &trusted_key {
+        signing::verify_snapshot(&old, key)
+            .with_context(|| format!("Snapshot #{} failed verification", from_index))?;
+        signing::verify_snapshot(&new, key)
+            .with_context(|| format!("Snapshot #{} failed verification", to_index))?;
+    }
+
+    let mut report = diff::compare_snapshots(
+        &old,
+        &new,
+        &format!("#{}", from_index),
+        &format!("#{}", to_index),
+    );
+    if ignore_cosmetic {
+        report = report.without_cosmetic();
+    }
+    let has_changes = report.has_changes();
+
+    match format {
+        "json" => diff::output::output_json(&report, output_file)?,
+        "markdown" | "md" => {
+            diff::output::output_markdown(&report, "ConfigTrace Ledger Diff", output_file)?
+        }
+        _ => diff::output::output_terminal(&report)?,
+    }
+
+    Ok(has_changes)
+}
+
+/// Delete all but the newest `keep` snapshots, signing the anchor left behind
+/// when a signing key is given.
+pub fn prune(store_dir: &str, keep: usize, sign_key: Option<&str>) -> Result<()> {
+    let key = sign_key.map(signing::load_signing_key).transpose()?;
+    let store = Store::open(store_dir)?;
+    let removed = store.prune(keep, key.as_ref())?;
+    println!(
+        "Pruned {} snapshot(s), kept {} in {}",
+        removed,
+        store.indices()?.len(),
+        store.root().display()
+    );
+    Ok(())
+}
+
+/// Verify the hash chain (and signatures) of the ledger.
+/// Returns true if the chain is broken.
+pub fn verify(
+    store_dir: &str,
+    format: &str,
+    output_file: Option<&str>,
+    verify_key: Option<&str>,
+) -> Result<bool> {
+    let trusted_key = verify_key.map(signing::load_verifying_key).transpose()?;
+    let store = Store::open(store_dir)?;
+    let report = store.verify_chain(trusted_key.as_ref())?;
+
+    match format {
+        "json" => output::output_verify_json(&report, output_file)?,
+        _ => output::output_verify_terminal(&report)?,
+    }
+
+    Ok(!report.intact)
+}
@@ -0,0 +1,46 @@
+use serde::{Deserialize, Serialize};
+
+use crate::models::SnapshotSignature;
+
+/// One snapshot in the ledger, as shown by `ledger list`.
+#[derive(Debug, Serialize)]
+pub struct LedgerEntry {
+    pub index: usize,
+    pub created_at: String,
+    pub file: String,
+    pub hash: String,
+    pub previous_hash: Option<String>,
+    pub total_files: usize,
+    pub signed: bool,
+}
+
+/// Marker left behind by `ledger prune` so the oldest remaining
+/// snapshot can still be checked against the one it replaced.
+/// Anyone could write one to hide early history, so it is signed like a snapshot.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PruneAnchor {
+    pub index: usize,
+    pub hash: String,
+    /// Present when pruned with `ledger prune --sign-key`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<SnapshotSignature>,
+}
+
+/// A broken link or invalid snapshot found while verifying the chain.
+#[derive(Debug, Serialize)]
+pub struct ChainIssue {
+    pub index: usize,
+    pub message: String,
+}
+
+/// Result of `ledger verify`.
+#[derive(Debug, Serialize)]
+pub struct ChainReport {
+    pub store: String,
+    pub total_snapshots: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub anchor: Option<PruneAnchor>,
+    pub intact: bool,
+    pub issues: Vec<ChainIssue>,
+}
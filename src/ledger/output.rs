@@ -0,0 +1,141 @@
+use anyhow::Result;
+use std::io::Write;
+use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
+
+use super::models::{ChainReport, LedgerEntry};
+use crate::models::Snapshot;
+
+// ===== Ledger List =====
+
+pub fn output_list_json(entries: &[LedgerEntry]) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(entries)?);
+    Ok(())
+}
+
+pub fn output_list_terminal(store: &str, entries: &[LedgerEntry]) -> Result<()> {
+    let mut stdout = StandardStream::stdout(ColorChoice::Auto);
+
+    writeln!(&mut stdout)?;
+    stdout.set_color(ColorSpec::new().set_bold(true))?;
+    writeln!(&mut stdout, "Snapshot Ledger: {}", store)?;
+    stdout.reset()?;
+    writeln!(&mut stdout)?;
+
+    if entries.is_empty() {
+        writeln!(&mut stdout, "  No snapshots recorded.")?;
+        writeln!(&mut stdout)?;
+        return Ok(());
+    }
+
+    writeln!(
+        &mut stdout,
+        "  {:<6} {:<34} {:>6}  {:<12}  Signed",
+        "#", "Created", "Files", "SHA256"
+    )?;
+    for entry in entries {
+        write!(&mut stdout, "  ")?;
+        stdout.set_color(ColorSpec::new().set_fg(Some(Color::Yellow)))?;
+        write!(&mut stdout, "{:<6}", entry.index)?;
+        stdout.reset()?;
+        writeln!(
+            &mut stdout,
+            " {:<34} {:>6}  {:<12}  {}",
+            entry.created_at,
+            entry.total_files,
+            &entry.hash[..12.min(entry.hash.len())],
+            if entry.signed { "yes" } else { "no" }
+        )?;
+    }
+    writeln!(&mut stdout)?;
+
+    Ok(())
+}
+
+// ===== Ledger Show =====
+
+pub fn output_show_terminal(index: usize, snapshot: &Snapshot) -> Result<()> {
+    let mut stdout = StandardStream::stdout(ColorChoice::Auto);
+
+    writeln!(&mut stdout)?;
+    stdout.set_color(ColorSpec::new().set_bold(true))?;
+    writeln!(&mut stdout, "Snapshot #{}", index)?;
+    stdout.reset()?;
+    writeln!(&mut stdout, "  Created:  {}", snapshot.created_at)?;
+    writeln!(
+        &mut stdout,
+        "  Previous: {}",
+        snapshot.previous_hash.as_deref().unwrap_or("(none)")
+    )?;
+    match &snapshot.signature {
+        Some(sig) => writeln!(&mut stdout, "  Signed:   {}", sig.fingerprint)?,
+        None => writeln!(&mut stdout, "  Signed:   no")?,
+    }
+    writeln!(&mut stdout, "  Files:    {}", snapshot.entries.len())?;
+    writeln!(&mut stdout)?;
+
+    for entry in &snapshot.entries {
+        writeln!(
+            &mut stdout,
+            "  {:<40} {}",
+            entry.path,
+            &entry.hash[..12.min(entry.hash.len())]
+        )?;
+    }
+    writeln!(&mut stdout)?;
+
+    Ok(())
+}
+
+// ===== Ledger Verify =====
+
+pub fn output_verify_json(report: &ChainReport, output_file: Option<&str>) -> Result<()> {
+    let json = serde_json::to_string_pretty(report)?;
+    if let Some(path) = output_file {
+        std::fs::write(path, &json)?;
+        println!("Wrote ledger verification to {}", path);
+    } else {
+        println!("{}", json);
+    }
+    Ok(())
+}
+
+pub fn output_verify_terminal(report: &ChainReport) -> Result<()> {
+    let mut stdout = StandardStream::stdout(ColorChoice::Auto);
+
+    writeln!(&mut stdout)?;
+    stdout.set_color(ColorSpec::new().set_bold(true))?;
+    writeln!(&mut stdout, "Verifying ledger: {}", report.store)?;
+    stdout.reset()?;
+    writeln!(&mut stdout, "  Snapshots: {}", report.total_snapshots)?;
+    if let Some(anchor) = &report.anchor {
+        writeln!(
+            &mut stdout,
+            "  Pruned up to #{} ({})",
+            anchor.index,
+            &anchor.hash[..12.min(anchor.hash.len())]
+        )?;
+    }
+    writeln!(&mut stdout)?;
+
+    for issue in &report.issues {
+        stdout.set_color(ColorSpec::new().set_fg(Some(Color::Red)).set_bold(true))?;
+        write!(&mut stdout, "BROKEN")?;
+        stdout.reset()?;
+        writeln!(&mut stdout, " #{}: {}", issue.index, issue.message)?;
+    }
+    if !report.issues.is_empty() {
+        writeln!(&mut stdout)?;
+    }
+
+    if report.intact {
+        stdout.set_color(ColorSpec::new().set_fg(Some(Color::Green)).set_bold(true))?;
+        writeln!(&mut stdout, "Chain intact.")?;
+    } else {
+        stdout.set_color(ColorSpec::new().set_fg(Some(Color::Red)).set_bold(true))?;
+        writeln!(&mut stdout, "Chain broken!")?;
+    }
+    stdout.reset()?;
+    writeln!(&mut stdout)?;
+
+    Ok(())
+}
@@ -0,0 +1,485 @@
+use anyhow::{Context, Result, bail, ensure};
+use chrono::{DateTime, NaiveDate, Utc};
+use ed25519_dalek::{SigningKey, VerifyingKey};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::models::Snapshot;
+use crate::signing;
+use crate::utils::hash_file;
+
+use super::models::{ChainIssue, ChainReport, LedgerEntry, PruneAnchor};
+
+const ANCHOR_FILE: &str = "anchor.json";
+
+/// A directory of numbered snapshots (`000001.json`, `000002.json`, ...)
+/// where each snapshot records the SHA-256 of the file before it.
+pub struct Store {
+    root: PathBuf,
+}
+
+impl Store {
+    /// Open an existing store.
+    pub fn open(dir: &str) -> Result<Self> {
+        ensure!(
+            Path::new(dir).is_dir(),
+            "No ledger at {}; record a snapshot with `configtrace ledger record` first",
+            dir
+        );
+        Ok(Store {
+            root: PathBuf::from(dir),
+        })
+    }
+
+    /// Open a store, creating the directory if it does not exist yet.
+    pub fn create(dir: &str) -> Result<Self> {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create ledger directory: {}", dir))?;
+        Store::open(dir)
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn snapshot_path(&self, index: usize) -> PathBuf {
+        self.root.join(format!("{:06}.json", index))
+    }
+
+    /// Indices of all stored snapshots, oldest first.
+    pub fn indices(&self) -> Result<Vec<usize>> {
+        let mut indices = Vec::new();
+        for entry in fs::read_dir(&self.root)? {
+            let name = entry?.file_name();
+            let Some(stem) = name.to_str().and_then(|n| n.strip_suffix(".json")) else {
+                continue;
+            };
+            if stem.len() == 6
+                && let Ok(index) = stem.parse::<usize>()
+            {
+                indices.push(index);
+            }
+        }
+        indices.sort_unstable();
+        Ok(indices)
+    }
+
+    pub fn load(&self, index: usize) -> Result<Snapshot> {
+        let path = self.snapshot_path(index);
+        let data =
+            fs::read(&path).with_context(|| format!("Snapshot #{} not found in ledger", index))?;
+        serde_json::from_slice(&data)
+            .with_context(|| format!("Failed to parse snapshot: {}", path.display()))
+    }
+
+    /// SHA-256 of a stored snapshot file, as recorded by its successor.
+    pub fn file_hash(&self, index: usize) -> Result<String> {
+        hash_file(&self.snapshot_path(index))
+    }
+
+    pub fn anchor(&self) -> Result<Option<PruneAnchor>> {
+        let path = self.root.join(ANCHOR_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let data = fs::read(&path)?;
+        Ok(Some(serde_json::from_slice(&data).with_context(|| {
+            format!("Failed to parse ledger anchor: {}", path.display())
+        })?))
+    }
+
+    /// Hash the next snapshot must link to: the newest stored snapshot,
+    /// or the prune anchor when every snapshot has been pruned.
+    pub fn head_hash(&self) -> Result<Option<String>> {
+        match self.indices()?.last() {
+            Some(&last) => Ok(Some(self.file_hash(last)?)),
+            None => Ok(self.anchor()?.map(|a| a.hash)),
+        }
+    }
+
+    fn next_index(&self) -> Result<usize> {
+        let last = self.indices()?.last().copied();
+        let anchored = self.anchor()?.map(|a| a.index);
+        Ok(last.or(anchored).unwrap_or(0) + 1)
+    }
+
+    /// Append a snapshot whose `previous_hash` is already linked to `head_hash`.
+    /// Returns the index it was stored under.
+    pub fn append(&self, snapshot: &Snapshot) -> Result<usize> {
+        ensure!(
+            snapshot.previous_hash == self.head_hash()?,
+            "Snapshot does not link to the current ledger head"
+        );
+        let index = self.next_index()?;
+        let path = self.snapshot_path(index);
+        fs::write(&path, serde_json::to_vec_pretty(snapshot)?)
+            .with_context(|| format!("Failed to write snapshot: {}", path.display()))?;
+        Ok(index)
+    }
+
+    /// Summaries of all stored snapshots, oldest first.
+    pub fn list(&self) -> Result<Vec<LedgerEntry>> {
+        let mut entries = Vec::new();
+        for index in self.indices()? {
+            let snapshot = self.load(index)?;
+            entries.push(LedgerEntry {
+                index,
+                created_at: snapshot.created_at,
+                file: self.snapshot_path(index).display().to_string(),
+                hash: self.file_hash(index)?,
+                previous_hash: snapshot.previous_hash,
+                total_files: snapshot.entries.len(),
+                signed: snapshot.signature.is_some(),
+            });
+        }
+        Ok(entries)
+    }
+
+    /// Resolve a snapshot reference: an index, `latest`, a date (`YYYY-MM-DD`)
+    /// or an RFC 3339 timestamp. Dates select the newest snapshot taken on or before them.
+    pub fn resolve(&self, reference: &str) -> Result<usize> {
+        let indices = self.indices()?;
+        if indices.is_empty() {
+            bail!("Ledger is empty: {}", self.root.display());
+        }
+
+        if reference == "latest" {
+            return Ok(*indices.last().unwrap());
+        }
+        if let Ok(index) = reference.parse::<usize>() {
+            ensure!(
+                indices.contains(&index),
+                "Snapshot #{} not found in ledger",
+                index
+            );
+            return Ok(index);
+        }
+
+        let cutoff = if let Ok(date) = NaiveDate::parse_from_str(reference, "%Y-%m-%d") {
+            date.and_hms_opt(23, 59, 59).unwrap().and_utc()
+        } else if let Ok(ts) = DateTime::parse_from_rfc3339(reference) {
+            ts.with_timezone(&Utc)
+        } else {
+            bail!(
+                "Invalid snapshot reference '{}': expected an index, 'latest', YYYY-MM-DD or an RFC 3339 timestamp",
+                reference
+            );
+        };
+
+        let mut found = None;
+        for index in indices {
+            let created = DateTime::parse_from_rfc3339(&self.load(index)?.created_at)
+                .with_context(|| format!("Snapshot #{} has an invalid timestamp", index))?;
+            if created.with_timezone(&Utc) <= cutoff {
+                found = Some(index);
+            }
+        }
+        found.with_context(|| format!("No snapshot taken on or before {}", reference))
+    }
+
+    /// Delete all but the newest `keep` snapshots, recording the last deleted
+    /// one as the anchor for chain verification, signed with `sign_key` if given.
+    /// Returns the number removed.
+    pub fn prune(&self, keep: usize, sign_key: Option<&SigningKey>) -> Result<usize> {
+        ensure!(keep > 0, "Must keep at least one snapshot");
+        let indices = self.indices()?;
+        if indices.len() <= keep {
+            return Ok(0);
+        }
+        let to_remove = &indices[..indices.len() - keep];
+        let last_removed = *to_remove.last().unwrap();
+
+        // Write the anchor first; verification ignores anything at or below it,
+        // so an interrupted prune still leaves a verifiable chain
+        let mut anchor = PruneAnchor {
+            index: last_removed,
+            hash: self.file_hash(last_removed)?,
+            signature: None,
+        };
+        if let Some(key) = sign_key {
+            signing::sign_anchor(&mut anchor, key)?;
+        }
+        fs::write(
+            self.root.join(ANCHOR_FILE),
+            serde_json::to_vec_pretty(&anchor)?,
+        )?;
+
+        for &index in to_remove {
+            fs::remove_file(self.snapshot_path(index))?;
+        }
+        Ok(to_remove.len())
+    }
+
+    /// Check that every snapshot links to its predecessor and, with a trusted key,
+    /// that every snapshot and the prune anchor are signed by it.
+    pub fn verify_chain(&self, trusted_key: Option<&VerifyingKey>) -> Result<ChainReport> {
+        let anchor = self.anchor()?;
+        let mut issues = Vec::new();
+        if let (Some(anchor), Some(key)) = (&anchor, trusted_key)
+            && let Err(e) = signing::verify_anchor(anchor, key)
+        {
+            issues.push(ChainIssue {
+                index: anchor.index,
+                message: format!("{:#}", e),
+            });
+        }
+        // Snapshots at or below the anchor are left over from an interrupted prune
+        let pruned_up_to = anchor.as_ref().map_or(0, |a| a.index);
+        let indices: Vec<usize> = self
+            .indices()?
+            .into_iter()
+            .filter(|&i| i > pruned_up_to)
+            .collect();
+
+        let mut expected_prev = anchor.as_ref().map(|a| (a.index, a.hash.clone()));
+        for &index in &indices {
+            let snapshot = match self.load(index) {
+                Ok(s) => s,
+                Err(e) => {
+                    issues.push(ChainIssue {
+                        index,
+                        message: format!("{:#}", e),
+                    });
+                    expected_prev = Some((index, self.file_hash(index)?));
+                    continue;
+                }
+            };
+
+            match (&expected_prev, &snapshot.previous_hash) {
+                (None, None) => {}
+                (None, Some(_)) => issues.push(ChainIssue {
+                    index,
+                    message: "First snapshot links to a predecessor that is not in the ledger"
+                        .to_string(),
+                }),
+                (Some((prev, _)), None) => issues.push(ChainIssue {
+                    index,
+                    message: format!("Missing link to snapshot #{}", prev),
+                }),
+                (Some((prev, hash)), Some(recorded)) if hash != recorded => {
+                    issues.push(ChainIssue {
+                        index,
+                        message: format!(
+                            "Link to snapshot #{} does not match (#{} was modified, removed or replaced)",
+                            prev, prev
+                        ),
+                    })
+                }
+                _ => {}
+            }
+
+            if let Some((prev, _)) = &expected_prev
+                && index != prev + 1
+            {
+                issues.push(ChainIssue {
+                    index,
+                    message: format!("Snapshots #{} to #{} are missing", prev + 1, index - 1),
+                });
+            }
+
+            if let Some(key) = trusted_key
+                && let Err(e) = signing::verify_snapshot(&snapshot, key)
+            {
+                issues.push(ChainIssue {
+                    index,
+                    message: format!("{:#}", e),
+                });
+            }
+
+            expected_prev = Some((index, self.file_hash(index)?));
+        }
+
+        Ok(ChainReport {
+            store: self.root.display().to_string(),
+            total_snapshots: indices.len(),
+            anchor,
+            intact: issues.is_empty(),
+            issues,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Entry;
+
+    fn record(store: &Store, created_at: &str, hash: &str) -> usize {
+        record_signed(store, created_at, hash, None)
+    }
+
+    fn record_signed(
+        store: &Store,
+        created_at: &str,
+        hash: &str,
+        key: Option<&SigningKey>,
+    ) -> usize {
+        let mut snapshot = Snapshot {
+            created_at: created_at.to_string(),
+            entries: vec![Entry {
+                path: "config.yaml".to_string(),
+                hash: hash.to_string(),
+                canonical_hash: None,
+                keys: None,
+                metadata: None,
+            }],
+            git_commit: None,
+            previous_hash: store.head_hash().unwrap(),
+            signature: None,
+        };
+        if let Some(key) = key {
+            signing::sign_snapshot(&mut snapshot, key).unwrap();
+        }
+        store.append(&snapshot).unwrap()
+    }
+
+    fn test_store() -> (tempfile::TempDir, Store) {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Store::open(dir.path().to_str().unwrap()).unwrap();
+        (dir, store)
+    }
+
+    #[test]
+    fn test_open_requires_existing_ledger() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("ledger");
+        let missing = missing.to_str().unwrap();
+        let err = Store::open(missing).err().unwrap();
+        assert!(err.to_string().contains("No ledger"), "{}", err);
+        assert!(!Path::new(missing).exists());
+
+        Store::create(missing).unwrap();
+        assert!(Store::open(missing).is_ok());
+    }
+
+    #[test]
+    fn test_append_builds_chain() {
+        let (_dir, store) = test_store();
+        assert_eq!(record(&store, "2025-01-01T10:00:00+00:00", "a"), 1);
+        assert_eq!(record(&store, "2025-01-02T10:00:00+00:00", "b"), 2);
+
+        let entries = store.list().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert!(entries[0].previous_hash.is_none());
+        assert_eq!(entries[1].previous_hash.as_ref(), Some(&entries[0].hash));
+        assert!(store.verify_chain(None).unwrap().intact);
+    }
+
+    #[test]
+    fn test_append_rejects_unlinked_snapshot() {
+        let (_dir, store) = test_store();
+        record(&store, "2025-01-01T10:00:00+00:00", "a");
+        let snapshot = Snapshot {
+            created_at: "2025-01-02T10:00:00+00:00".to_string(),
+            entries: Vec::new(),
+            git_commit: None,
+            previous_hash: None,
+            signature: None,
+        };
+        assert!(store.append(&snapshot).is_err());
+    }
+
+    #[test]
+    fn test_tampered_snapshot_breaks_chain() {
+        let (_dir, store) = test_store();
+        record(&store, "2025-01-01T10:00:00+00:00", "a");
+        record(&store, "2025-01-02T10:00:00+00:00", "b");
+
+        let path = store.snapshot_path(1);
+        let content = fs::read_to_string(&path).unwrap().replace("\"a\"", "\"x\"");
+        fs::write(&path, content).unwrap();
+
+        let report = store.verify_chain(None).unwrap();
+        assert!(!report.intact);
+        assert_eq!(report.issues[0].index, 2);
+    }
+
+    #[test]
+    fn test_removed_snapshot_breaks_chain() {
+        let (_dir, store) = test_store();
+        record(&store, "2025-01-01T10:00:00+00:00", "a");
+        record(&store, "2025-01-02T10:00:00+00:00", "b");
+        record(&store, "2025-01-03T10:00:00+00:00", "c");
+        fs::remove_file(store.snapshot_path(2)).unwrap();
+
+        let report = store.verify_chain(None).unwrap();
+        assert!(!report.intact);
+        assert!(report.issues.iter().any(|i| i.message.contains("missing")));
+    }
+
+    #[test]
+    fn test_prune_keeps_chain_verifiable() {
+        let (_dir, store) = test_store();
+        for day in 1..=4 {
+            record(&store, &format!("2025-01-0{}T10:00:00+00:00", day), "h");
+        }
+        assert_eq!(store.prune(2, None).unwrap(), 2);
+        assert_eq!(store.indices().unwrap(), vec![3, 4]);
+        assert_eq!(store.anchor().unwrap().unwrap().index, 2);
+        assert!(store.verify_chain(None).unwrap().intact);
+
+        // New snapshots keep numbering after the anchor
+        assert_eq!(record(&store, "2025-01-05T10:00:00+00:00", "h"), 5);
+        assert!(store.verify_chain(None).unwrap().intact);
+    }
+
+    #[test]
+    fn test_prune_anchor_must_be_signed_by_trusted_key() {
+        let (_dir, store) = test_store();
+        let key = SigningKey::from_bytes(&[1; 32]);
+        for day in 1..=4 {
+            let created_at = format!("2025-01-0{}T10:00:00+00:00", day);
+            record_signed(&store, &created_at, "h", Some(&key));
+        }
+        store.prune(2, Some(&key)).unwrap();
+        let trusted = key.verifying_key();
+        assert!(store.verify_chain(Some(&trusted)).unwrap().intact);
+
+        // Hiding #3 behind a forged anchor keeps the links intact, but not the signature
+        let forged = PruneAnchor {
+            index: 3,
+            hash: store.file_hash(3).unwrap(),
+            signature: store.anchor().unwrap().unwrap().signature,
+        };
+        fs::write(
+            store.root().join(ANCHOR_FILE),
+            serde_json::to_vec(&forged).unwrap(),
+        )
+        .unwrap();
+        fs::remove_file(store.snapshot_path(3)).unwrap();
+        assert!(store.verify_chain(None).unwrap().intact);
+        let report = store.verify_chain(Some(&trusted)).unwrap();
+        assert!(!report.intact);
+        assert!(report.issues[0].message.contains("Prune anchor"));
+
+        // An unsigned anchor is refused as well
+        let unsigned = PruneAnchor {
+            signature: None,
+            ..forged
+        };
+        fs::write(
+            store.root().join(ANCHOR_FILE),
+            serde_json::to_vec(&unsigned).unwrap(),
+        )
+        .unwrap();
+        let report = store.verify_chain(Some(&trusted)).unwrap();
+        assert!(report.issues[0].message.contains("not signed"));
+    }
+
+    #[test]
+    fn test_resolve_references() {
+        let (_dir, store) = test_store();
+        record(&store, "2025-01-01T10:00:00+00:00", "a");
+        record(&store, "2025-01-03T10:00:00+00:00", "b");
+        record(&store, "2025-01-03T18:00:00+00:00", "c");
+
+        assert_eq!(store.resolve("2").unwrap(), 2);
+        assert_eq!(store.resolve("latest").unwrap(), 3);
+        assert_eq!(store.resolve("2025-01-02").unwrap(), 1);
+        assert_eq!(store.resolve("2025-01-03").unwrap(), 3);
+        assert_eq!(store.resolve("2025-01-03T12:00:00Z").unwrap(), 2);
+        assert!(store.resolve("2024-12-31").is_err());
+        assert!(store.resolve("9").is_err());
+        assert!(store.resolve("yesterday").is_err());
+    }
+}
@@ -1,37 +1,116 @@
 use anyhow::Result;
 use clap::{Parser, Subcommand};
 
+mod archive;
+mod cache;
 mod diff;
+mod format;
 mod git;
+mod keypath;
+mod ledger;
 mod models;
 mod policy;
 mod report;
 mod scan;
 mod secrets;
+mod signing;
+mod source;
 mod utils;
+mod verify;
+mod walk;
 
 #[derive(Parser)]
 #[command(name = "configtrace", about = "Track & audit configuration changes")]
 struct Cli {
+    /// Reuse per-file results from .configtrace/cache.json, so only changed files are re-processed
+    #[arg(long, global = true)]
+    cache: bool,
+    /// Like --cache, with the cache stored in this file
+    #[arg(long, global = true, value_name = "PATH")]
+    cache_file: Option<String>,
+    /// Number of worker threads for hashing, parsing and scanning (0 = one per CPU)
+    #[arg(long, global = true, default_value_t = 0)]
+    jobs: usize,
+    /// Settings file mapping globs to formats (default: .configtrace.yaml when present)
+    #[arg(long, global = true, value_name = "FILE")]
+    config: Option<String>,
     #[command(subcommand)]
     command: Commands,
 }
 
 #[derive(Subcommand)]
 enum Commands {
-    /// Scan a directory and produce a snapshot (hashes of YAML/JSON/TOML)
+    /// Scan a directory and produce a snapshot (hashes of YAML, JSON/JSONC/JSON5, TOML, dotenv, INI, properties, HCL and XML files)
     Scan {
         path: String,
         #[arg(long, default_value = "snapshot.json")]
         out: String,
+        /// Embed each file's flattened keys so `diff` can show key-level changes
+        #[arg(long)]
+        keys: bool,
+        /// Sign the snapshot with an Ed25519 key created by `keygen`
+        #[arg(long)]
+        sign_key: Option<String>,
+        /// Read files from this git ref (branch, tag or commit) instead of the working
+        /// directory; PATH is then relative to the repository root
+        #[arg(long = "ref", value_name = "REF")]
+        git_ref: Option<String>,
+        #[command(flatten)]
+        walk: walk::WalkOptions,
+    },
+    /// Generate an Ed25519 key pair for signing snapshots
+    Keygen {
+        /// Path for the secret key (public key is written to <out>.pub)
+        #[arg(long, default_value = "configtrace.key")]
+        out: String,
+    },
+    /// Compare two snapshots and print a diff (key-level when captured with --keys)
+    Diff {
+        old: String,
+        new: String,
+        /// Output format: text, json, or markdown
+        #[arg(long, default_value = "text")]
+        format: String,
+        /// Write output to file instead of stdout
+        #[arg(long)]
+        output: Option<String>,
+        /// Exit with code 1 when any file was added, removed or changed
+        #[arg(long)]
+        fail_on_change: bool,
+        /// Require both snapshots to be signed by this public key
+        #[arg(long)]
+        verify_key: Option<String>,
+        /// Ignore files whose changes are only cosmetic (formatting, key order, comments)
+        #[arg(long)]
+        ignore_cosmetic: bool,
+    },
+    /// Check a directory for drift against a baseline snapshot
+    Verify {
+        /// Directory to verify (use the same path the baseline was scanned with)
+        path: String,
+        /// Baseline snapshot produced by `scan`
+        #[arg(long)]
+        snapshot: String,
+        /// Output format: text, json, or markdown
+        #[arg(long, default_value = "text")]
+        format: String,
+        /// Write output to file instead of stdout
+        #[arg(long)]
+        output: Option<String>,
+        /// Require the baseline to be signed by this public key
+        #[arg(long)]
+        verify_key: Option<String>,
+        /// Ignore files whose changes are only cosmetic (formatting, key order, comments)
+        #[arg(long)]
+        ignore_cosmetic: bool,
+        #[command(flatten)]
+        walk: walk::WalkOptions,
     },
-    /// Compare two snapshots and print a simple diff
-    Diff { old: String, new: String },
     /// Generate a unified audit report (inventory, secrets, policy, git)
     Report {
         /// Path to directory to audit
         path: String,
-        /// Path to policy file for policy evaluation
+        /// Path to policy file for policy evaluation; without it only duplicate keys are checked
         #[arg(long)]
         policy: Option<String>,
         /// Output format: text, json, or markdown
@@ -40,6 +119,8 @@ enum Commands {
         /// Write output to file instead of stdout
         #[arg(long)]
         output: Option<String>,
+        #[command(flatten)]
+        walk: walk::WalkOptions,
     },
     /// Scan for secrets in configuration files
     Secrets {
@@ -48,6 +129,8 @@ enum Commands {
         format: String,
         #[arg(long)]
         output: Option<String>,
+        #[command(flatten)]
+        walk: walk::WalkOptions,
     },
     /// Check configuration files against a policy
     Policy {
@@ -59,6 +142,14 @@ enum Commands {
         #[command(subcommand)]
         action: GitAction,
     },
+    /// Manage a hash-chained history of snapshots
+    Ledger {
+        /// Ledger directory
+        #[arg(long, global = true, default_value = ".configtrace/ledger")]
+        store: String,
+        #[command(subcommand)]
+        action: LedgerAction,
+    },
 }
 
 #[derive(Subcommand)]
@@ -67,15 +158,17 @@ enum PolicyAction {
     Check {
         /// Path to directory or file to check
         path: String,
-        /// Path to the policy YAML file
+        /// Path to the policy YAML file; without it only duplicate keys are checked
         #[arg(long)]
-        policy: String,
+        policy: Option<String>,
         /// Output format: text or json
         #[arg(long, default_value = "text")]
         format: String,
         /// Write output to file instead of stdout
         #[arg(long)]
         output: Option<String>,
+        #[command(flatten)]
+        walk: walk::WalkOptions,
     },
     /// Validate a policy file without running checks
     Validate {
@@ -84,6 +177,75 @@ enum PolicyAction {
     },
 }
 
+#[derive(Subcommand)]
+enum LedgerAction {
+    /// Scan a directory and append the snapshot to the ledger
+    Record {
+        path: String,
+        /// Embed each file's flattened keys for key-level diffs
+        #[arg(long)]
+        keys: bool,
+        /// Sign the snapshot with an Ed25519 key created by `keygen`
+        #[arg(long)]
+        sign_key: Option<String>,
+        #[command(flatten)]
+        walk: walk::WalkOptions,
+    },
+    /// List recorded snapshots, oldest first
+    List {
+        /// Output format: text or json
+        #[arg(long, default_value = "text")]
+        format: String,
+    },
+    /// Show a snapshot by index, `latest`, or date (YYYY-MM-DD)
+    Show {
+        snapshot: String,
+        /// Output format: text or json
+        #[arg(long, default_value = "text")]
+        format: String,
+    },
+    /// Compare two snapshots by index, `latest`, or date (YYYY-MM-DD)
+    Diff {
+        from: String,
+        to: String,
+        /// Output format: text, json, or markdown
+        #[arg(long, default_value = "text")]
+        format: String,
+        /// Write output to file instead of stdout
+        #[arg(long)]
+        output: Option<String>,
+        /// Require both snapshots to be signed by this public key
+        #[arg(long)]
+        verify_key: Option<String>,
+        /// Exit with code 1 when any file was added, removed or changed
+        #[arg(long)]
+        fail_on_change: bool,
+        /// Ignore files whose changes are only cosmetic (formatting, key order, comments)
+        #[arg(long)]
+        ignore_cosmetic: bool,
+    },
+    /// Delete old snapshots, keeping the newest N
+    Prune {
+        #[arg(long)]
+        keep: usize,
+        /// Sign the anchor that records the pruned history with this secret key
+        #[arg(long)]
+        sign_key: Option<String>,
+    },
+    /// Verify that the snapshot chain is unbroken
+    Verify {
+        /// Output format: text or json
+        #[arg(long, default_value = "text")]
+        format: String,
+        /// Write output to file instead of stdout
+        #[arg(long)]
+        output: Option<String>,
+        /// Require every snapshot to be signed by this public key
+        #[arg(long)]
+        verify_key: Option<String>,
+    },
+}
+
 #[derive(Subcommand)]
 enum GitAction {
     /// Show config change history across git commits
@@ -123,19 +285,101 @@ enum GitAction {
     },
 }
 
+impl Cli {
+    /// Cache file to use, if caching is enabled.
+    fn cache_path(&self) -> Option<&str> {
+        match &self.cache_file {
+            Some(path) => Some(path),
+            None => self.cache.then_some(DEFAULT_CACHE_FILE),
+        }
+    }
+}
+
+const DEFAULT_CACHE_FILE: &str = ".configtrace/cache.json";
+
 fn main() -> Result<()> {
     let cli = Cli::parse();
+    walk::init_jobs(cli.jobs)?;
+    format::init(cli.config.as_deref())?;
+    let cache = cache::Cache::open(cli.cache_path())?;
+
     match cli.command {
-        Commands::Scan { path, out } => scan::scan(&path, &out)?,
-        Commands::Diff { old, new } => diff::diff(&old, &new)?,
+        Commands::Scan {
+            path,
+            out,
+            keys,
+            sign_key,
+            git_ref,
+            walk,
+        } => scan::scan(
+            &path,
+            &out,
+            keys,
+            sign_key.as_deref(),
+            git_ref.as_deref(),
+            &walk,
+            &cache,
+        )?,
+        Commands::Keygen { out } => signing::generate_keypair(&out)?,
+        Commands::Diff {
+            old,
+            new,
+            format,
+            output,
+            fail_on_change,
+            verify_key,
+            ignore_cosmetic,
+        } => {
+            let has_changes = diff::diff(
+                &old,
+                &new,
+                &format,
+                output.as_deref(),
+                verify_key.as_deref(),
+                ignore_cosmetic,
+            )?;
+            if has_changes && fail_on_change {
+                std::process::exit(1);
+            }
+        }
+        Commands::Verify {
+            path,
+            snapshot,
+            format,
+            output,
+            verify_key,
+            ignore_cosmetic,
+            walk,
+        } => {
+            let has_drift = verify::verify(
+                &path,
+                &snapshot,
+                &format,
+                output.as_deref(),
+                verify_key.as_deref(),
+                ignore_cosmetic,
+                &walk,
+                &cache,
+            )?;
+            if has_drift {
+                std::process::exit(1);
+            }
+        }
         Commands::Report {
             path,
             policy,
             format,
             output,
+            walk,
         } => {
-            let is_fail =
-                report::generate_report(&path, policy.as_deref(), &format, output.as_deref())?;
+            let is_fail = report::generate_report(
+                &path,
+                policy.as_deref(),
+                &format,
+                output.as_deref(),
+                &walk,
+                &cache,
+            )?;
             if is_fail {
                 std::process::exit(1);
             }
@@ -144,8 +388,10 @@ fn main() -> Result<()> {
             path,
             format,
             output,
+            walk,
         } => {
-            let has_secrets = secrets::scan_for_secrets(&path, &format, output.as_deref())?;
+            let has_secrets =
+                secrets::scan_for_secrets(&path, &format, output.as_deref(), &walk, &cache)?;
             if has_secrets {
                 std::process::exit(1);
             }
@@ -156,9 +402,16 @@ fn main() -> Result<()> {
                 policy: policy_path,
                 format,
                 output,
+                walk,
             } => {
-                let has_violations =
-                    policy::check_policy(&path, &policy_path, &format, output.as_deref())?;
+                let has_violations = policy::check_policy(
+                    &path,
+                    policy_path.as_deref(),
+                    &format,
+                    output.as_deref(),
+                    &walk,
+                    &cache,
+                )?;
                 if has_violations {
                     std::process::exit(1);
                 }
@@ -209,6 +462,84 @@ fn main() -> Result<()> {
                 }
             }
         },
+        Commands::Ledger { store, action } => match action {
+            LedgerAction::Record {
+                path,
+                keys,
+                sign_key,
+                walk,
+            } => ledger::record(&store, &path, keys, sign_key.as_deref(), &walk, &cache)?,
+            LedgerAction::List { format } => ledger::list(&store, &format)?,
+            LedgerAction::Show { snapshot, format } => ledger::show(&store, &snapshot, &format)?,
+            LedgerAction::Diff {
+                from,
+                to,
+                format,
+                output,
+                verify_key,
+                fail_on_change,
+                ignore_cosmetic,
+            } => {
+                let has_changes = ledger::diff(
+                    &store,
+                    &from,
+                    &to,
+                    &format,
+                    output.as_deref(),
+                    verify_key.as_deref(),
+                    ignore_cosmetic,
+                )?;
+                if has_changes && fail_on_change {
+                    std::process::exit(1);
+                }
+            }
+            LedgerAction::Prune { keep, sign_key } => {
+                ledger::prune(&store, keep, sign_key.as_deref())?
+            }
+            LedgerAction::Verify {
+                format,
+                output,
+                verify_key,
+            } => {
+                let is_broken =
+                    ledger::verify(&store, &format, output.as_deref(), verify_key.as_deref())?;
+                if is_broken {
+                    std::process::exit(1);
+                }
+            }
+        },
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Cli {
+        Cli::try_parse_from([&["configtrace"], args].concat()).unwrap()
+    }
+
+    #[test]
+    fn test_cache_flag_does_not_take_a_value() {
+        for args in [
+            &["--cache", "report", "./configs"][..],
+            &["report", "--cache", "./configs"],
+            &["report", "./configs", "--cache"],
+            &["scan", "--cache", "infra", "--out", "s.json"],
+        ] {
+            let cli = parse(args);
+            assert_eq!(cli.cache_path(), Some(DEFAULT_CACHE_FILE), "{:?}", args);
+        }
+        let cli = parse(&["scan", "--cache", "infra", "--out", "s.json"]);
+        assert!(matches!(cli.command, Commands::Scan { path, .. } if path == "infra"));
+
+        for args in [
+            &["--cache-file", "/tmp/c.json", "report", "./configs"][..],
+            &["report", "./configs", "--cache-file", "/tmp/c.json"],
+        ] {
+            assert_eq!(parse(args).cache_path(), Some("/tmp/c.json"), "{:?}", args);
+        }
+        assert_eq!(parse(&["report", "./configs"]).cache_path(), None);
+    }
+}
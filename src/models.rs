@@ -1,15 +1,314 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
-#[derive(Serialize, Deserialize)]
+use crate::keypath::KeyPath;
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct Entry {
     pub path: String,
     pub hash: String,
+    /// SHA-256 of the parsed, key-sorted content. Unchanged by cosmetic edits
+    /// such as reordering keys, reindenting or comments. Absent if the file did not parse.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub canonical_hash: Option<String>,
+    /// Flattened key map of the file, embedded when scanning with `--keys`.
+    /// Lets `diff` report key-level changes without access to the files.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keys: Option<BTreeMap<String, FlatValue>>,
+    /// Permissions, ownership and link target. Absent in older snapshots.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<FileMetadata>,
+}
+
+/// Filesystem metadata of a config file. Mode and ownership are Unix-only.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct FileMetadata {
+    /// Permission bits, e.g. 0o600.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uid: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gid: Option<u32>,
+    pub size: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mtime: Option<String>,
+    /// Where the path points when it is a symlink.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub symlink_target: Option<String>,
+}
+
+/// Where a flattened key is written in its file. Line and column are 1-based.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl std::fmt::Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
+/// The YAML anchor a value was inherited from, through an alias (`*defaults`)
+/// or a merge key (`<<: *defaults`).
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Origin {
+    pub anchor: String,
+    /// Where the value is written inside the anchored node.
+    pub span: Span,
+}
+
+impl std::fmt::Display for Origin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "inherited from anchor `{}` at line {}",
+            self.anchor, self.span.line
+        )
+    }
+}
+
+/// A key written more than once in the same YAML or JSON map. The last value is kept.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct DuplicateKey {
+    pub key: KeyPath,
+    /// Where the key is written again.
+    pub span: Span,
+    /// Where the key is first written.
+    pub first: Span,
+}
+
+/// Location shown next to a key: `line 7, column 3`, followed by the anchor when
+/// the value is inherited (`..., inherited from anchor `defaults` at line 2`).
+pub fn describe_location(span: Option<Span>, origin: Option<&Origin>) -> Option<String> {
+    let span = span?;
+    Some(match origin {
+        Some(origin) => format!("{}, {}", span, origin),
+        None => span.to_string(),
+    })
+}
+
+/// A scalar from a flattened config, keeping its type so `5432` and `"5432"` differ.
+/// Empty maps and lists have no keys below them, so they are kept as markers.
+#[derive(Debug, Clone)]
+pub enum FlatValue {
+    String(String),
+    Int(i64),
+    /// An integer above `i64::MAX`.
+    UInt(u64),
+    /// May be NaN or infinite (YAML `.nan`, `.inf`), see `NON_FINITE_KEY`.
+    Float(f64),
+    Bool(bool),
+    Null,
+    EmptyObject,
+    EmptyArray,
+}
+
+/// Type of a config value, as named in `value_type` policy checks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ValueType {
+    String,
+    Int,
+    Float,
+    Bool,
+    Null,
+    Object,
+    Array,
+}
+
+impl std::fmt::Display for ValueType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            ValueType::String => "string",
+            ValueType::Int => "int",
+            ValueType::Float => "float",
+            ValueType::Bool => "bool",
+            ValueType::Null => "null",
+            ValueType::Object => "object",
+            ValueType::Array => "array",
+        };
+        f.write_str(name)
+    }
 }
 
-#[derive(Serialize, Deserialize)]
+/// JSON has no NaN or infinity, so a non-finite float is written as a one-key
+/// object, `{"$float": ".nan"}`, using the YAML spelling.
+const NON_FINITE_KEY: &str = "$float";
+
+impl FlatValue {
+    pub fn value_type(&self) -> ValueType {
+        match self {
+            FlatValue::String(_) => ValueType::String,
+            FlatValue::Int(_) | FlatValue::UInt(_) => ValueType::Int,
+            FlatValue::Float(_) => ValueType::Float,
+            FlatValue::Bool(_) => ValueType::Bool,
+            FlatValue::Null => ValueType::Null,
+            FlatValue::EmptyObject => ValueType::Object,
+            FlatValue::EmptyArray => ValueType::Array,
+        }
+    }
+
+    /// The value as it would be written in JSON, so strings are quoted: `"5432"` vs `5432`.
+    pub fn to_json(&self) -> serde_json::Value {
+        match self {
+            FlatValue::String(s) => serde_json::Value::String(s.clone()),
+            FlatValue::Int(i) => (*i).into(),
+            FlatValue::UInt(u) => (*u).into(),
+            FlatValue::Float(f) => match serde_json::Number::from_f64(*f) {
+                Some(n) => serde_json::Value::Number(n),
+                None => serde_json::json!({ NON_FINITE_KEY: non_finite_name(*f) }),
+            },
+            FlatValue::Bool(b) => serde_json::Value::Bool(*b),
+            FlatValue::Null => serde_json::Value::Null,
+            FlatValue::EmptyObject => serde_json::Value::Object(Default::default()),
+            FlatValue::EmptyArray => serde_json::Value::Array(Vec::new()),
+        }
+    }
+
+    /// Convert a JSON scalar, empty container or encoded non-finite float;
+    /// None for any other map or list.
+    pub fn from_json(value: &serde_json::Value) -> Option<FlatValue> {
+        Some(match value {
+            serde_json::Value::String(s) => FlatValue::String(s.clone()),
+            serde_json::Value::Number(n) => match (n.as_i64(), n.as_u64()) {
+                (Some(i), _) => FlatValue::Int(i),
+                (None, Some(u)) => FlatValue::UInt(u),
+                _ => FlatValue::Float(n.as_f64()?),
+            },
+            serde_json::Value::Bool(b) => FlatValue::Bool(*b),
+            serde_json::Value::Null => FlatValue::Null,
+            serde_json::Value::Object(obj) if obj.is_empty() => FlatValue::EmptyObject,
+            serde_json::Value::Object(obj) if obj.len() == 1 => {
+                let name = obj.get(NON_FINITE_KEY)?.as_str()?;
+                FlatValue::Float(parse_non_finite(name)?)
+            }
+            serde_json::Value::Array(arr) if arr.is_empty() => FlatValue::EmptyArray,
+            _ => return None,
+        })
+    }
+
+    /// Convert a YAML scalar or empty container; None for a non-empty map or list,
+    /// or a tagged value.
+    pub fn from_yaml(value: &serde_yaml::Value) -> Option<FlatValue> {
+        Some(match value {
+            serde_yaml::Value::String(s) => FlatValue::String(s.clone()),
+            serde_yaml::Value::Number(n) => match (n.as_i64(), n.as_u64()) {
+                (Some(i), _) => FlatValue::Int(i),
+                (None, Some(u)) => FlatValue::UInt(u),
+                _ => FlatValue::Float(n.as_f64()?),
+            },
+            serde_yaml::Value::Bool(b) => FlatValue::Bool(*b),
+            serde_yaml::Value::Null => FlatValue::Null,
+            serde_yaml::Value::Mapping(map) if map.is_empty() => FlatValue::EmptyObject,
+            serde_yaml::Value::Sequence(seq) if seq.is_empty() => FlatValue::EmptyArray,
+            _ => return None,
+        })
+    }
+}
+
+fn non_finite_name(f: f64) -> &'static str {
+    if f.is_nan() {
+        ".nan"
+    } else if f > 0.0 {
+        ".inf"
+    } else {
+        "-.inf"
+    }
+}
+
+fn parse_non_finite(name: &str) -> Option<f64> {
+    match name {
+        ".nan" => Some(f64::NAN),
+        ".inf" => Some(f64::INFINITY),
+        "-.inf" => Some(f64::NEG_INFINITY),
+        _ => None,
+    }
+}
+
+/// Values are equal when they have the same type and value; NaN equals NaN, so an
+/// unchanged `.nan` is not reported as a change.
+impl PartialEq for FlatValue {
+    fn eq(&self, other: &FlatValue) -> bool {
+        match (self, other) {
+            (FlatValue::String(a), FlatValue::String(b)) => a == b,
+            (FlatValue::Int(a), FlatValue::Int(b)) => a == b,
+            (FlatValue::UInt(a), FlatValue::UInt(b)) => a == b,
+            (FlatValue::Float(a), FlatValue::Float(b)) => a == b || (a.is_nan() && b.is_nan()),
+            (FlatValue::Bool(a), FlatValue::Bool(b)) => a == b,
+            (FlatValue::Null, FlatValue::Null)
+            | (FlatValue::EmptyObject, FlatValue::EmptyObject)
+            | (FlatValue::EmptyArray, FlatValue::EmptyArray) => true,
+            _ => false,
+        }
+    }
+}
+
+impl From<&str> for FlatValue {
+    fn from(s: &str) -> FlatValue {
+        FlatValue::String(s.to_string())
+    }
+}
+
+/// Plain rendering used by policy checks and most output: `5432`, `true`, `null`, `{}`, `.nan`.
+impl std::fmt::Display for FlatValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FlatValue::String(s) => f.write_str(s),
+            FlatValue::Float(x) if !x.is_finite() => f.write_str(non_finite_name(*x)),
+            other => write!(f, "{}", other.to_json()),
+        }
+    }
+}
+
+impl Serialize for FlatValue {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.to_json().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for FlatValue {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = serde_json::Value::deserialize(deserializer)?;
+        FlatValue::from_json(&value).ok_or_else(|| {
+            serde::de::Error::custom("expected a scalar, {}, [] or {\"$float\": ...}")
+        })
+    }
+}
+
+/// Snapshots and their entries refuse unknown fields, so a signature covers
+/// everything in the file.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct Snapshot {
     pub created_at: String,
     pub entries: Vec<Entry>,
+    /// Commit the snapshot was taken from with `scan --ref`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git_commit: Option<String>,
+    /// SHA-256 of the previous snapshot file when stored in a ledger.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous_hash: Option<String>,
+    /// Present when the snapshot was signed with `scan --sign-key`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<SnapshotSignature>,
+}
+
+/// Detached signature over a snapshot's or prune anchor's content (everything except this field).
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct SnapshotSignature {
+    pub algorithm: String,
+    /// Hex-encoded public key of the signer.
+    pub public_key: String,
+    /// SHA-256 fingerprint of the public key.
+    pub fingerprint: String,
+    /// Hex-encoded signature bytes.
+    pub signature: String,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
@@ -33,7 +332,7 @@ pub enum Severity {
     High,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct SecretFinding {
     pub secret_type: SecretType,
     pub severity: Severity,
@@ -46,6 +345,9 @@ pub struct SecretFinding {
 pub struct FileSecrets {
     pub path: String,
     pub findings: Vec<SecretFinding>,
+    /// Octal mode, set when the file is readable by its group or by everyone.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exposed_mode: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -56,5 +358,8 @@ pub struct SecretReport {
     pub total_findings: usize,
     pub critical_count: usize,
     pub high_count: usize,
+    /// Files with findings that are group- or world-readable.
+    #[serde(default)]
+    pub exposed_files: usize,
     pub files: Vec<FileSecrets>,
 }
@@ -1,53 +1,79 @@
 use anyhow::Result;
 use chrono::Utc;
-use std::collections::HashMap;
+use rayon::prelude::*;
 use std::path::Path;
-use walkdir::WalkDir;
 
-use crate::utils::is_config;
+use crate::cache::{self, Cache};
+use crate::models::ValueType;
+use crate::walk::{self, WalkOptions};
+
+use super::parser::{Document, FlatMap, SourceMap, split_documents};
 
 use super::models::*;
-use super::parser::parse_config_file;
+
+/// Rule id of the built-in check for keys written twice in the same map.
+pub const DUPLICATE_KEY_RULE: &str = "duplicate-key";
 
 /// Evaluate all rules in a policy against all config files under the given path.
-pub fn evaluate_policy(path: &str, policy: &PolicyFile) -> Result<PolicyReport> {
+/// Per-file results are cached against a hash of the policy.
+pub fn evaluate_policy(
+    path: &str,
+    policy: &PolicyFile,
+    walk: &WalkOptions,
+    cache: &Cache,
+) -> Result<PolicyReport> {
+    let policy_hash = cache::fingerprint(policy);
+    let files = walk::config_files(path, walk)?;
+    let results = files
+        .par_iter()
+        .map(|file_path| {
+            let flat_map = match cache.parse(file_path) {
+                Ok(map) => map,
+                Err(e) => {
+                    eprintln!("Warning: Could not parse {}: {}", file_path.display(), e);
+                    return Ok(Vec::new());
+                }
+            };
+            let source_map = cache.source_map(file_path)?;
+            cache.violations(file_path, &policy_hash, || {
+                let documents = split_documents(&flat_map, &source_map);
+                let mut violations: Vec<Violation> = policy
+                    .rules
+                    .iter()
+                    .filter(|rule| rule_applies_to_file(rule, file_path.path()))
+                    .flat_map(|rule| {
+                        evaluate_rule_on_documents(
+                            rule,
+                            file_path.path(),
+                            &flat_map,
+                            &documents,
+                            &source_map,
+                        )
+                    })
+                    .collect();
+                violations.extend(duplicate_key_violations(
+                    policy,
+                    file_path.path(),
+                    &source_map,
+                ));
+                violations
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
     let mut all_file_violations = Vec::new();
-    let mut total_files = 0;
     let mut critical_count = 0;
     let mut high_count = 0;
     let mut medium_count = 0;
     let mut low_count = 0;
 
-    for entry in WalkDir::new(path).into_iter().filter_map(|e| e.ok()) {
-        let file_path = entry.path();
-        if !file_path.is_file() || !is_config(file_path) {
-            continue;
-        }
-        total_files += 1;
-
-        let flat_map = match parse_config_file(file_path) {
-            Ok(map) => map,
-            Err(e) => {
-                eprintln!("Warning: Could not parse {}: {}", file_path.display(), e);
-                continue;
-            }
-        };
-
-        let mut violations = Vec::new();
-
-        for rule in &policy.rules {
-            if !rule_applies_to_file(rule, file_path) {
-                continue;
-            }
-
-            if let Some(violation) = evaluate_rule(rule, file_path, &flat_map) {
-                match violation.severity {
-                    PolicySeverity::Critical => critical_count += 1,
-                    PolicySeverity::High => high_count += 1,
-                    PolicySeverity::Medium => medium_count += 1,
-                    PolicySeverity::Low => low_count += 1,
-                }
-                violations.push(violation);
+    for (file_path, violations) in files.iter().zip(results) {
+        for violation in &violations {
+            match violation.severity {
+                PolicySeverity::Critical => critical_count += 1,
+                PolicySeverity::High => high_count += 1,
+                PolicySeverity::Medium => medium_count += 1,
+                PolicySeverity::Low => low_count += 1,
             }
         }
 
@@ -65,7 +91,7 @@ pub fn evaluate_policy(path: &str, policy: &PolicyFile) -> Result<PolicyReport>
         checked_at: Utc::now().to_rfc3339(),
         policy_name: policy.name.clone(),
         policy_description: policy.description.clone(),
-        total_files_checked: total_files,
+        total_files_checked: files.len(),
         files_with_violations: all_file_violations.len(),
         total_violations,
         critical_count,
@@ -95,19 +121,111 @@ pub(crate) fn rule_applies_to_file(rule: &RuleDefinition, file_path: &Path) -> b
     }
 }
 
+/// Evaluate a rule against each document of a file that its `kind` or `document`
+/// filter selects. A rule without a filter, or whose key names a document itself
+/// (`[Deployment/api].spec.replicas`), is checked against the whole file instead.
+/// Violations point at the key's line and column when `source_map` has them,
+/// and at the anchor when the value is inherited from one.
+pub(crate) fn evaluate_rule_on_documents(
+    rule: &RuleDefinition,
+    file_path: &Path,
+    flat_map: &FlatMap,
+    documents: &[Document],
+    source_map: &SourceMap,
+) -> Vec<Violation> {
+    let per_document = rule.kind.is_some() || rule.document.is_some();
+    let mut violations: Vec<Violation> = if !per_document || rule.check.key().names_document() {
+        evaluate_rule(rule, file_path, flat_map)
+            .into_iter()
+            .collect()
+    } else {
+        evaluate_rule_per_document(rule, file_path, documents)
+    };
+    for violation in &mut violations {
+        violation.span = source_map.span(&violation.key);
+        violation.origin = source_map.origin(&violation.key).cloned();
+    }
+    violations
+}
+
+fn evaluate_rule_per_document(
+    rule: &RuleDefinition,
+    file_path: &Path,
+    documents: &[Document],
+) -> Vec<Violation> {
+    documents
+        .iter()
+        .filter(|doc| rule_applies_to_document(rule, doc))
+        .filter_map(|doc| {
+            let mut violation = evaluate_rule(rule, file_path, &doc.keys)?;
+            if let Some(prefix) = &doc.prefix {
+                violation.key = prefix.join(&violation.key);
+            }
+            Some(violation)
+        })
+        .collect()
+}
+
+/// Check if a rule's `kind` and document pattern match the document.
+fn rule_applies_to_document(rule: &RuleDefinition, doc: &Document) -> bool {
+    if let Some(kind) = &rule.kind
+        && doc.resource.as_ref().is_none_or(|r| &r.kind != kind)
+    {
+        return false;
+    }
+    match &rule.document {
+        None => true,
+        Some(pat) => {
+            let glob_pattern = glob::Pattern::new(pat).unwrap(); // Pre-validated in loader
+            doc.id.as_deref().is_some_and(|id| glob_pattern.matches(id))
+        }
+    }
+}
+
+/// Violations of the built-in `duplicate-key` check, at the severity the policy
+/// sets with `duplicate_keys`. Each points at the repeated key.
+pub(crate) fn duplicate_key_violations(
+    policy: &PolicyFile,
+    file_path: &Path,
+    source_map: &SourceMap,
+) -> Vec<Violation> {
+    let Some(severity) = &policy.duplicate_keys else {
+        return Vec::new();
+    };
+    source_map
+        .duplicates()
+        .iter()
+        .map(|duplicate| Violation {
+            rule_id: DUPLICATE_KEY_RULE.to_string(),
+            rule_description: Some("Keys must not be repeated in the same map".to_string()),
+            severity: severity.clone(),
+            file: file_path.display().to_string(),
+            key: duplicate.key.clone(),
+            message: format!(
+                "Key '{}' is repeated; this value replaces the one at line {}",
+                duplicate.key, duplicate.first.line
+            ),
+            span: Some(duplicate.span),
+            origin: None,
+        })
+        .collect()
+}
+
 /// Evaluate a single rule against a flattened config map.
 /// Returns Some(Violation) if the rule is violated, None if compliant.
 pub(crate) fn evaluate_rule(
     rule: &RuleDefinition,
     file_path: &Path,
-    flat_map: &HashMap<String, String>,
+    flat_map: &FlatMap,
 ) -> Option<Violation> {
     let file_str = file_path.display().to_string();
+    // Flattened keys are written the way `KeyPath` renders
+    let key = rule.check.key().resolve(|k| has_key(flat_map, k));
+    let flat_key = key.to_string();
 
     match &rule.check {
-        CheckDefinition::RequiredKey { key } => {
-            let key_exists = flat_map.contains_key(key)
-                || flat_map.keys().any(|k| k.starts_with(&format!("{}.", key)));
+        CheckDefinition::RequiredKey { .. } => {
+            let key_exists = has_key(flat_map, &flat_key);
 
             if !key_exists {
                 return Some(Violation {
@@ -117,13 +235,14 @@ pub(crate) fn evaluate_rule(
                     file: file_str,
                     key: key.clone(),
                     message: format!("Required key '{}' is missing", key),
+                    span: None,
+                    origin: None,
                 });
             }
         }
 
-        CheckDefinition::ForbiddenKey { key } => {
-            let key_exists = flat_map.contains_key(key)
-                || flat_map.keys().any(|k| k.starts_with(&format!("{}.", key)));
+        CheckDefinition::ForbiddenKey { .. } => {
+            let key_exists = has_key(flat_map, &flat_key);
 
             if key_exists {
                 return Some(Violation {
@@ -133,14 +252,16 @@ pub(crate) fn evaluate_rule(
                     file: file_str,
                     key: key.clone(),
                     message: format!("Forbidden key '{}' is present", key),
+                    span: None,
+                    origin: None,
                 });
             }
         }
 
-        CheckDefinition::ValueMatch { key, regex } => {
-            if let Some(value) = flat_map.get(key) {
+        CheckDefinition::ValueMatch { regex, .. } => {
+            if let Some(value) = flat_map.get(&flat_key) {
                 let re = regex::Regex::new(regex).unwrap(); // Pre-validated
-                if !re.is_match(value) {
+                if !re.is_match(&value.to_string()) {
                     return Some(Violation {
                         rule_id: rule.id.clone(),
                         rule_description: rule.description.clone(),
@@ -151,14 +272,16 @@ pub(crate) fn evaluate_rule(
                             "Value '{}' for key '{}' does not match pattern '{}'",
                             value, key, regex
                         ),
+                        span: None,
+                        origin: None,
                     });
                 }
             }
         }
 
-        CheckDefinition::ValueEnum { key, values } => {
-            if let Some(value) = flat_map.get(key)
-                && !values.contains(value)
+        CheckDefinition::ValueEnum { values, .. } => {
+            if let Some(value) = flat_map.get(&flat_key)
+                && !values.contains(&value.to_string())
             {
                 return Some(Violation {
                     rule_id: rule.id.clone(),
@@ -172,13 +295,15 @@ pub(crate) fn evaluate_rule(
                         key,
                         values.join(", ")
                     ),
+                    span: None,
+                    origin: None,
                 });
             }
         }
 
-        CheckDefinition::ForbiddenValue { key, value } => {
-            if let Some(actual) = flat_map.get(key)
-                && actual == value
+        CheckDefinition::ForbiddenValue { value, .. } => {
+            if let Some(actual) = flat_map.get(&flat_key)
+                && actual.to_string() == *value
             {
                 return Some(Violation {
                     rule_id: rule.id.clone(),
@@ -187,6 +312,28 @@ pub(crate) fn evaluate_rule(
                     file: file_str,
                     key: key.clone(),
                     message: format!("Forbidden value '{}' found for key '{}'", value, key),
+                    span: None,
+                    origin: None,
+                });
+            }
+        }
+
+        CheckDefinition::ValueType { value_type, .. } => {
+            if let Some(actual) = value_type_of(flat_map, &flat_key)
+                && actual != *value_type
+            {
+                return Some(Violation {
+                    rule_id: rule.id.clone(),
+                    rule_description: rule.description.clone(),
+                    severity: rule.severity.clone(),
+                    file: file_str,
+                    key: key.clone(),
+                    message: format!(
+                        "Key '{}' is of type {}, expected {}",
+                        key, actual, value_type
+                    ),
+                    span: None,
+                    origin: None,
                 });
             }
         }
@@ -195,14 +342,43 @@ pub(crate) fn evaluate_rule(
     None
 }
 
+/// Whether `key` is in the map, or has keys below it.
+fn has_key(flat_map: &FlatMap, key: &str) -> bool {
+    flat_map.contains_key(key)
+        || flat_map.keys().any(|k| {
+            k.strip_prefix(key)
+                .is_some_and(|rest| rest.starts_with(['.', '[']))
+        })
+}
+
+/// Type of the value at `key`. A key with keys below it is an object or array.
+/// None when the key is missing.
+fn value_type_of(flat_map: &FlatMap, key: &str) -> Option<ValueType> {
+    if let Some(value) = flat_map.get(key) {
+        return Some(value.value_type());
+    }
+    flat_map.keys().find_map(|k| {
+        let rest = k.strip_prefix(key)?;
+        // A quoted key (`a["b.c"]`) is a map key, not a list position
+        if rest.starts_with('.') || rest.starts_with("[\"") {
+            Some(ValueType::Object)
+        } else if rest.starts_with('[') {
+            Some(ValueType::Array)
+        } else {
+            None
+        }
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::models::FlatValue;
 
-    fn make_map(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+    fn make_map(pairs: &[(&str, &str)]) -> FlatMap {
         pairs
             .iter()
-            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .map(|(k, v)| (k.to_string(), FlatValue::from(*v)))
             .collect()
     }
 
@@ -212,6 +388,8 @@ mod tests {
             description: Some(id.to_string()),
             severity,
             pattern: None,
+            document: None,
+            kind: None,
             check,
         }
     }
@@ -223,7 +401,7 @@ mod tests {
             "r1",
             PolicySeverity::High,
             CheckDefinition::RequiredKey {
-                key: "logging.level".to_string(),
+                key: "logging.level".parse().unwrap(),
             },
         );
         let result = evaluate_rule(&rule, Path::new("test.yaml"), &map);
@@ -237,7 +415,7 @@ mod tests {
             "r1",
             PolicySeverity::High,
             CheckDefinition::RequiredKey {
-                key: "logging".to_string(),
+                key: "logging".parse().unwrap(),
             },
         );
         let result = evaluate_rule(&rule, Path::new("test.yaml"), &map);
@@ -251,7 +429,7 @@ mod tests {
             "r1",
             PolicySeverity::High,
             CheckDefinition::RequiredKey {
-                key: "logging.level".to_string(),
+                key: "logging.level".parse().unwrap(),
             },
         );
         let result = evaluate_rule(&rule, Path::new("test.yaml"), &map);
@@ -266,7 +444,7 @@ mod tests {
             "r1",
             PolicySeverity::Critical,
             CheckDefinition::ForbiddenKey {
-                key: "debug".to_string(),
+                key: "debug".parse().unwrap(),
             },
         );
         let result = evaluate_rule(&rule, Path::new("test.yaml"), &map);
@@ -280,7 +458,7 @@ mod tests {
             "r1",
             PolicySeverity::Critical,
             CheckDefinition::ForbiddenKey {
-                key: "debug".to_string(),
+                key: "debug".parse().unwrap(),
             },
         );
         let result = evaluate_rule(&rule, Path::new("test.yaml"), &map);
@@ -295,7 +473,7 @@ mod tests {
             "r1",
             PolicySeverity::High,
             CheckDefinition::ValueMatch {
-                key: "aws.region".to_string(),
+                key: "aws.region".parse().unwrap(),
                 regex: "^us-".to_string(),
             },
         );
@@ -310,7 +488,7 @@ mod tests {
             "r1",
             PolicySeverity::High,
             CheckDefinition::ValueMatch {
-                key: "aws.region".to_string(),
+                key: "aws.region".parse().unwrap(),
                 regex: "^us-".to_string(),
             },
         );
@@ -326,7 +504,7 @@ mod tests {
             "r1",
             PolicySeverity::High,
             CheckDefinition::ValueMatch {
-                key: "aws.region".to_string(),
+                key: "aws.region".parse().unwrap(),
                 regex: "^us-".to_string(),
             },
         );
@@ -341,7 +519,7 @@ mod tests {
             "r1",
             PolicySeverity::Medium,
             CheckDefinition::ValueEnum {
-                key: "logging.level".to_string(),
+                key: "logging.level".parse().unwrap(),
                 values: vec!["debug".into(), "info".into(), "warn".into(), "error".into()],
             },
         );
@@ -356,7 +534,7 @@ mod tests {
             "r1",
             PolicySeverity::Medium,
             CheckDefinition::ValueEnum {
-                key: "logging.level".to_string(),
+                key: "logging.level".parse().unwrap(),
                 values: vec!["debug".into(), "info".into(), "warn".into(), "error".into()],
             },
         );
@@ -372,7 +550,7 @@ mod tests {
             "r1",
             PolicySeverity::Critical,
             CheckDefinition::ForbiddenValue {
-                key: "debug".to_string(),
+                key: "debug".parse().unwrap(),
                 value: "true".to_string(),
             },
         );
@@ -387,7 +565,7 @@ mod tests {
             "r1",
             PolicySeverity::Critical,
             CheckDefinition::ForbiddenValue {
-                key: "debug".to_string(),
+                key: "debug".parse().unwrap(),
                 value: "true".to_string(),
             },
         );
@@ -402,7 +580,7 @@ mod tests {
             "r1",
             PolicySeverity::Low,
             CheckDefinition::RequiredKey {
-                key: "x".to_string(),
+                key: "x".parse().unwrap(),
             },
         );
         assert!(rule_applies_to_file(&rule, Path::new("anything.yaml")));
@@ -414,11 +592,214 @@ mod tests {
             "r1",
             PolicySeverity::Low,
             CheckDefinition::RequiredKey {
-                key: "x".to_string(),
+                key: "x".parse().unwrap(),
             },
         );
         rule.pattern = Some("*.yaml".to_string());
         assert!(rule_applies_to_file(&rule, Path::new("config.yaml")));
         assert!(!rule_applies_to_file(&rule, Path::new("config.json")));
     }
+
+    #[test]
+    fn test_rule_evaluated_per_document() {
+        use crate::policy::parser::{parse_config_content, parse_config_source_map};
+
+        let content = "apiVersion: apps/v1\nkind: Deployment\nmetadata:\n  name: api\nspec:\n  replicas: '1'\n---\nspec:\n  replicas: '3'\n---\napiVersion: v1\nkind: Service\nmetadata:\n  name: api\n";
+        let map = parse_config_content(content, Path::new("bundle.yaml")).unwrap();
+        let spans = parse_config_source_map(content, Path::new("bundle.yaml"));
+        let documents = split_documents(&map, &spans);
+        let mut rule = make_rule(
+            "replicas",
+            PolicySeverity::High,
+            CheckDefinition::RequiredKey {
+                key: "spec.replicas".parse().unwrap(),
+            },
+        );
+        // Without a filter the rule sees the file as a whole, as before documents were split
+        let violations =
+            evaluate_rule_on_documents(&rule, Path::new("bundle.yaml"), &map, &documents, &spans);
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].key, "spec.replicas");
+
+        rule.document = Some("*".to_string());
+        let violations =
+            evaluate_rule_on_documents(&rule, Path::new("bundle.yaml"), &map, &documents, &spans);
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].key, "[v1/Service/api].spec.replicas");
+
+        rule.document = Some("apps/v1/Deployment/*".to_string());
+        let violations =
+            evaluate_rule_on_documents(&rule, Path::new("bundle.yaml"), &map, &documents, &spans);
+        assert!(violations.is_empty());
+
+        rule.document = Some("*".to_string());
+        rule.check = CheckDefinition::ValueEnum {
+            key: "spec.replicas".parse().unwrap(),
+            values: vec!["3".to_string()],
+        };
+        let violations =
+            evaluate_rule_on_documents(&rule, Path::new("bundle.yaml"), &map, &documents, &spans);
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].key, "[apps/v1/Deployment/api].spec.replicas");
+
+        // Only documents with a full identity are resources of a kind
+        rule.document = None;
+        rule.kind = Some("Deployment".to_string());
+        rule.check = CheckDefinition::ValueEnum {
+            key: "spec.replicas".parse().unwrap(),
+            values: vec!["1".to_string()],
+        };
+        let violations =
+            evaluate_rule_on_documents(&rule, Path::new("bundle.yaml"), &map, &documents, &spans);
+        assert!(violations.is_empty());
+    }
+
+    #[test]
+    fn test_violation_points_at_key() {
+        use crate::policy::parser::{parse_config_content, parse_config_source_map};
+
+        let content = "server:\n  port: 80\n  tls: false\n";
+        let path = Path::new("app.yaml");
+        let map = parse_config_content(content, path).unwrap();
+        let spans = parse_config_source_map(content, path);
+        let documents = split_documents(&map, &spans);
+        let mut rule = make_rule(
+            "tls",
+            PolicySeverity::High,
+            CheckDefinition::ForbiddenValue {
+                key: "server.tls".parse().unwrap(),
+                value: "false".to_string(),
+            },
+        );
+        let violations = evaluate_rule_on_documents(&rule, path, &map, &documents, &spans);
+        let span = violations[0].span.unwrap();
+        assert_eq!((span.line, span.column), (3, 3));
+
+        // A missing key points at its closest parent
+        rule.check = CheckDefinition::RequiredKey {
+            key: "server.tls.cert".parse().unwrap(),
+        };
+        let violations = evaluate_rule_on_documents(&rule, path, &map, &documents, &spans);
+        assert_eq!(violations[0].span.map(|s| s.line), Some(3));
+    }
+
+    #[test]
+    fn test_value_type() {
+        let mut map = make_map(&[("db.port", "5432"), ("db.hosts[0]", "a")]);
+        let mut rule = make_rule(
+            "port-type",
+            PolicySeverity::Medium,
+            CheckDefinition::ValueType {
+                key: "db.port".parse().unwrap(),
+                value_type: ValueType::Int,
+            },
+        );
+        let v = evaluate_rule(&rule, Path::new("app.yaml"), &map).unwrap();
+        assert_eq!(v.message, "Key 'db.port' is of type string, expected int");
+
+        map.insert("db.port".to_string(), FlatValue::Int(5432));
+        assert!(evaluate_rule(&rule, Path::new("app.yaml"), &map).is_none());
+
+        rule.check = CheckDefinition::ValueType {
+            key: "db.hosts".parse().unwrap(),
+            value_type: ValueType::Array,
+        };
+        assert!(evaluate_rule(&rule, Path::new("app.yaml"), &map).is_none());
+        rule.check = CheckDefinition::ValueType {
+            key: "db".parse().unwrap(),
+            value_type: ValueType::Object,
+        };
+        assert!(evaluate_rule(&rule, Path::new("app.yaml"), &map).is_none());
+    }
+
+    #[test]
+    fn test_escaped_and_pointer_keys() {
+        use crate::policy::parser::{parse_config_content, parse_config_source_map};
+
+        let content = "metadata:\n  labels:\n    app.kubernetes.io/name: api\n  ports: [80]\n";
+        let path = Path::new("deploy.yaml");
+        let map = parse_config_content(content, path).unwrap();
+        let spans = parse_config_source_map(content, path);
+        let documents = split_documents(&map, &spans);
+        let mut rule = make_rule(
+            "name",
+            PolicySeverity::Low,
+            CheckDefinition::ValueEnum {
+                key: r#"metadata.labels["app.kubernetes.io/name"]"#.parse().unwrap(),
+                values: vec!["web".to_string()],
+            },
+        );
+        let violations = evaluate_rule_on_documents(&rule, path, &map, &documents, &spans);
+        assert_eq!(
+            violations[0].key,
+            r#"metadata.labels["app.kubernetes.io/name"]"#
+        );
+        assert_eq!(violations[0].span.map(|s| s.line), Some(3));
+
+        // The same key as a JSON Pointer; the unescaped dotted path is a different key
+        rule.check = CheckDefinition::ForbiddenKey {
+            key: "/metadata/labels/app.kubernetes.io~1name".parse().unwrap(),
+        };
+        assert!(evaluate_rule(&rule, path, &map).is_some());
+        rule.check = CheckDefinition::ForbiddenKey {
+            key: "metadata.labels.app".parse().unwrap(),
+        };
+        assert!(evaluate_rule(&rule, path, &map).is_none());
+
+        // A non-empty list has keys below it, so it is present
+        rule.check = CheckDefinition::RequiredKey {
+            key: "metadata.ports".parse().unwrap(),
+        };
+        assert!(evaluate_rule(&rule, path, &map).is_none());
+        rule.check = CheckDefinition::ValueType {
+            key: "metadata.labels".parse().unwrap(),
+            value_type: ValueType::Object,
+        };
+        assert!(evaluate_rule(&rule, path, &map).is_none());
+    }
+
+    #[test]
+    fn test_pointer_numeric_tokens_follow_the_container() {
+        let map = make_map(&[("codes.404", "/missing"), ("servers[0].host", "db")]);
+        let mut rule = make_rule(
+            "not-found",
+            PolicySeverity::Low,
+            CheckDefinition::ForbiddenKey {
+                key: "/codes/404".parse().unwrap(),
+            },
+        );
+        let violation = evaluate_rule(&rule, Path::new("app.json"), &map).unwrap();
+        assert_eq!(violation.key, "codes.404");
+
+        rule.check = CheckDefinition::ValueEnum {
+            key: "/servers/0/host".parse().unwrap(),
+            values: vec!["cache".to_string()],
+        };
+        let violation = evaluate_rule(&rule, Path::new("app.json"), &map).unwrap();
+        assert_eq!(violation.key, "servers[0].host");
+    }
+
+    #[test]
+    fn test_duplicate_key_violations() {
+        use crate::policy::parser::parse_config_source_map;
+
+        let path = Path::new("app.json");
+        let spans = parse_config_source_map("{\"debug\": false,\n \"debug\": true}", path);
+        let mut policy = PolicyFile {
+            name: "dupes".to_string(),
+            description: None,
+            rules: Vec::new(),
+            duplicate_keys: Some(PolicySeverity::High),
+        };
+        let violations = duplicate_key_violations(&policy, path, &spans);
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].rule_id, DUPLICATE_KEY_RULE);
+        assert_eq!(violations[0].severity, PolicySeverity::High);
+        assert_eq!(violations[0].key, "debug");
+        assert_eq!(violations[0].span.map(|s| s.line), Some(2));
+        assert!(violations[0].message.contains("line 1"));
+
+        policy.duplicate_keys = None;
+        assert!(duplicate_key_violations(&policy, path, &spans).is_empty());
+    }
 }
@@ -2,7 +2,11 @@ use anyhow::{Context, Result, ensure};
 use std::collections::HashSet;
 use std::path::Path;
 
-use super::models::{CheckDefinition, PolicyFile};
+use super::evaluator::DUPLICATE_KEY_RULE;
+use super::models::{CheckDefinition, PolicyFile, PolicySeverity};
+
+/// Name of the policy used when no policy file is given.
+pub const BUILTIN_POLICY: &str = "built-in checks";
 
 /// Load and validate a policy file from disk.
 pub fn load_policy(path: &Path) -> Result<PolicyFile> {
@@ -16,6 +20,17 @@ pub fn load_policy(path: &Path) -> Result<PolicyFile> {
     Ok(policy)
 }
 
+/// The policy used without `--policy`: no rules, only the built-in duplicate key
+/// check at medium severity.
+pub fn builtin_policy() -> PolicyFile {
+    PolicyFile {
+        name: BUILTIN_POLICY.to_string(),
+        description: None,
+        rules: Vec::new(),
+        duplicate_keys: Some(PolicySeverity::Medium),
+    }
+}
+
 /// Validate that the policy is well-formed.
 fn validate_policy(policy: &PolicyFile) -> Result<()> {
     ensure!(
@@ -30,6 +45,11 @@ fn validate_policy(policy: &PolicyFile) -> Result<()> {
             "Duplicate rule id: '{}'",
             rule.id
         );
+        ensure!(
+            rule.id != DUPLICATE_KEY_RULE,
+            "Rule id '{}' is reserved for the built-in duplicate key check",
+            rule.id
+        );
 
         // Validate regex compiles for value_match rules
         if let CheckDefinition::ValueMatch { regex, .. } = &rule.check {
@@ -43,6 +63,14 @@ fn validate_policy(policy: &PolicyFile) -> Result<()> {
                 format!("Invalid glob pattern in rule '{}': {}", rule.id, pattern)
             })?;
         }
+        if let Some(document) = &rule.document {
+            glob::Pattern::new(document).with_context(|| {
+                format!(
+                    "Invalid document pattern in rule '{}': {}",
+                    rule.id, document
+                )
+            })?;
+        }
     }
 
     Ok(())
@@ -160,5 +188,27 @@ rules:
             policy.rules[0].severity,
             super::super::models::PolicySeverity::Medium
         );
+        assert_eq!(policy.duplicate_keys, None);
+    }
+
+    #[test]
+    fn test_load_policy_duplicate_keys_setting() {
+        let rules = "rules:\n  - id: r\n    check:\n      type: required_key\n      key: a\n";
+        let file = write_policy_file(&format!("name: p\nduplicate_keys: off\n{}", rules));
+        assert_eq!(load_policy(file.path()).unwrap().duplicate_keys, None);
+
+        let file = write_policy_file(&format!("name: p\nduplicate_keys: high\n{}", rules));
+        assert_eq!(
+            load_policy(file.path()).unwrap().duplicate_keys,
+            Some(super::super::models::PolicySeverity::High)
+        );
+
+        let file = write_policy_file(&format!("name: p\nduplicate_keys: loud\n{}", rules));
+        assert!(load_policy(file.path()).is_err());
+
+        let reserved = rules.replace("id: r", "id: duplicate-key");
+        let file = write_policy_file(&format!("name: p\n{}", reserved));
+        let err = load_policy(file.path()).unwrap_err();
+        assert!(err.to_string().contains("reserved"), "{}", err);
     }
 }
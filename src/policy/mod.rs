@@ -7,15 +7,25 @@ pub(crate) mod parser;
 use anyhow::Result;
 use std::path::Path;
 
-/// Check configs against a policy file. Returns true if violations were found.
+use crate::cache::Cache;
+use crate::walk::WalkOptions;
+
+/// Check configs against a policy file, or only for duplicate keys without one.
+/// Returns true if violations were found.
 pub fn check_policy(
     path: &str,
-    policy_path: &str,
+    policy_path: Option<&str>,
     format: &str,
     output_file: Option<&str>,
+    walk: &WalkOptions,
+    cache: &Cache,
 ) -> Result<bool> {
-    let policy = loader::load_policy(Path::new(policy_path))?;
-    let report = evaluator::evaluate_policy(path, &policy)?;
+    let policy = match policy_path {
+        Some(policy_path) => loader::load_policy(Path::new(policy_path))?,
+        None => loader::builtin_policy(),
+    };
+    let report = evaluator::evaluate_policy(path, &policy, walk, cache)?;
+    cache.save()?;
     let has_violations = report.total_violations > 0;
 
     match format {
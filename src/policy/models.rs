@@ -1,5 +1,8 @@
 use serde::{Deserialize, Serialize};
 
+use crate::keypath::KeyPath;
+use crate::models::{Origin, Span, ValueType};
+
 /// Four-level severity for policy violations.
 /// Separate from secrets::Severity to allow independent evolution.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -26,18 +29,49 @@ fn default_severity() -> PolicySeverity {
     PolicySeverity::Medium
 }
 
+/// A severity, or `off` (None).
+mod severity_or_off {
+    use super::PolicySeverity;
+    use serde::de::IntoDeserializer;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        severity: &Option<PolicySeverity>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match severity {
+            Some(severity) => severity.serialize(serializer),
+            None => serializer.serialize_str("off"),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<PolicySeverity>, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        if value == "off" {
+            return Ok(None);
+        }
+        PolicySeverity::deserialize(value.as_str().into_deserializer()).map(Some)
+    }
+}
+
 // ========== Policy File Definition (deserialized from YAML) ==========
 
 /// Top-level policy file structure.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct PolicyFile {
     pub name: String,
     pub description: Option<String>,
     pub rules: Vec<RuleDefinition>,
+    /// Severity of the built-in `duplicate-key` check, which flags a key written twice
+    /// in the same YAML or JSON map. `off`, the default, disables it.
+    #[serde(default, with = "severity_or_off")]
+    pub duplicate_keys: Option<PolicySeverity>,
 }
 
 /// A single rule within a policy file.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct RuleDefinition {
     pub id: String,
     pub description: Option<String>,
@@ -46,36 +80,73 @@ pub struct RuleDefinition {
     /// Glob pattern for which config files this rule applies to.
     /// If omitted, the rule applies to all config files.
     pub pattern: Option<String>,
+    /// Glob on document identity (`apps/v1/Deployment/*`, or a position like `0`) selecting
+    /// which documents of a multi-document file this rule applies to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub document: Option<String>,
+    /// Only apply to Kubernetes-style resources of this kind (e.g. `Deployment`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
     pub check: CheckDefinition,
 }
 
 /// The check to perform, represented as a tagged enum.
-#[derive(Debug, Deserialize)]
+/// Keys are dotted paths or JSON Pointers, see `KeyPath`.
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum CheckDefinition {
     /// A key must exist in the config.
-    RequiredKey { key: String },
+    RequiredKey { key: KeyPath },
     /// A key must NOT exist in the config.
-    ForbiddenKey { key: String },
+    ForbiddenKey { key: KeyPath },
     /// A key's value must match a regex pattern.
-    ValueMatch { key: String, regex: String },
+    ValueMatch { key: KeyPath, regex: String },
     /// A key's value must be one of the allowed values.
-    ValueEnum { key: String, values: Vec<String> },
+    ValueEnum { key: KeyPath, values: Vec<String> },
     /// A specific key-value combination must not exist.
-    ForbiddenValue { key: String, value: String },
+    ForbiddenValue { key: KeyPath, value: String },
+    /// A key's value must be of this type, e.g. `int` rather than the string `"5432"`.
+    ValueType { key: KeyPath, value_type: ValueType },
+}
+
+impl CheckDefinition {
+    /// The config key this check inspects.
+    pub fn key(&self) -> &KeyPath {
+        match self {
+            CheckDefinition::RequiredKey { key }
+            | CheckDefinition::ForbiddenKey { key }
+            | CheckDefinition::ValueMatch { key, .. }
+            | CheckDefinition::ValueEnum { key, .. }
+            | CheckDefinition::ForbiddenValue { key, .. }
+            | CheckDefinition::ValueType { key, .. } => key,
+        }
+    }
 }
 
 // ========== Evaluation Results (for output) ==========
 
 /// A single violation produced when a rule check fails.
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Violation {
     pub rule_id: String,
     pub rule_description: Option<String>,
     pub severity: PolicySeverity,
     pub file: String,
-    pub key: String,
+    pub key: KeyPath,
     pub message: String,
+    /// Where the key (or its closest existing parent) is written in the file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub span: Option<Span>,
+    /// Set when the value is inherited from a YAML anchor written elsewhere.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub origin: Option<Origin>,
+}
+
+impl Violation {
+    /// Where the violation is, as shown next to it in terminal and markdown output.
+    pub fn location(&self) -> Option<String> {
+        crate::models::describe_location(self.span, self.origin.as_ref())
+    }
 }
 
 /// Violations grouped by file.
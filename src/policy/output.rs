@@ -58,7 +58,12 @@ pub fn output_terminal(report: &PolicyReport) -> Result<()> {
             if let Some(desc) = &violation.rule_description {
                 writeln!(&mut stdout, "    Rule: {}", desc)?;
             }
-            writeln!(&mut stdout, "    Key: {}", violation.key)?;
+            match violation.location() {
+                Some(location) => {
+                    writeln!(&mut stdout, "    Key: {} ({})", violation.key, location)?
+                }
+                None => writeln!(&mut stdout, "    Key: {}", violation.key)?,
+            }
             writeln!(&mut stdout)?;
         }
     }
@@ -0,0 +1,148 @@
+use std::collections::{HashMap, HashSet};
+
+use super::{FlatMap, SourceMap, flatten};
+use crate::keypath::{KeyPath, split_document};
+use crate::models::FlatValue;
+
+/// Identity of a Kubernetes-style resource: a document with `apiVersion`, `kind` and `metadata.name`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Resource {
+    pub api_version: String,
+    pub kind: String,
+    pub namespace: Option<String>,
+    pub name: String,
+}
+
+impl Resource {
+    /// Read the identity from a document's flattened keys.
+    pub fn from_keys(keys: &FlatMap) -> Option<Resource> {
+        Some(Resource {
+            api_version: keys.get("apiVersion")?.to_string(),
+            kind: keys.get("kind")?.to_string(),
+            namespace: keys.get("metadata.namespace").map(FlatValue::to_string),
+            name: keys.get("metadata.name")?.to_string(),
+        })
+    }
+
+    /// `apps/v1/Deployment/prod/api`, or `v1/Namespace/prod` for a resource without a namespace.
+    pub fn identity(&self) -> String {
+        match &self.namespace {
+            Some(ns) => format!("{}/{}/{}/{}", self.api_version, self.kind, ns, self.name),
+            None => format!("{}/{}/{}", self.api_version, self.kind, self.name),
+        }
+    }
+}
+
+/// One document of a parsed file, so rules can check documents individually.
+pub struct Document {
+    /// Resource identity, otherwise the position in a multi-document file.
+    pub id: Option<String>,
+    /// Set when the document is a Kubernetes-style resource.
+    pub resource: Option<Resource>,
+    /// Prefix the document's keys carry in the file's flat map, e.g. `[apps/v1/Deployment/prod/api]`.
+    pub prefix: Option<KeyPath>,
+    /// The document's keys, without the prefix.
+    pub keys: FlatMap,
+}
+
+/// Flatten a multi-document file, prefixing each document's keys with its resource
+/// identity (`[apps/v1/Deployment/prod/api].spec.replicas`) or, without one,
+/// its position (`[2].name`).
+pub(super) fn flatten_documents(documents: &[serde_yaml::Value]) -> FlatMap {
+    let mut map = HashMap::new();
+    for (id, keys) in document_ids(documents) {
+        for (key, value) in keys {
+            map.insert(prefixed(&id, &key), value);
+        }
+    }
+    map
+}
+
+/// Flatten each document of a multi-document file, along with the id its keys are prefixed with.
+pub(super) fn document_ids(documents: &[serde_yaml::Value]) -> Vec<(String, FlatMap)> {
+    let mut seen = HashSet::new();
+    documents
+        .iter()
+        .enumerate()
+        .map(|(index, document)| {
+            let mut keys = HashMap::new();
+            flatten("", document, &mut keys);
+            // A repeated identity falls back to the position so no keys collide
+            let id = Resource::from_keys(&keys)
+                .map(|r| r.identity())
+                .filter(|id| seen.insert(id.clone()))
+                .unwrap_or_else(|| index.to_string());
+            (id, keys)
+        })
+        .collect()
+}
+
+/// Split a flat map back into its documents, in file order. Only a YAML stream of
+/// several documents, as listed in its `source_map`, is split. Any other file,
+/// including one whose top level is a list, is one document holding every key.
+pub fn split_documents(map: &FlatMap, source_map: &SourceMap) -> Vec<Document> {
+    if source_map.documents().is_empty() {
+        let resource = Resource::from_keys(map);
+        return vec![Document {
+            id: resource.as_ref().map(Resource::identity),
+            resource,
+            prefix: None,
+            keys: map.clone(),
+        }];
+    }
+
+    let mut documents: HashMap<&str, FlatMap> = HashMap::new();
+    for (key, value) in map {
+        if let Some((id, rest)) = split_document(key) {
+            documents
+                .entry(id)
+                .or_default()
+                .insert(rest.to_string(), value.clone());
+        }
+    }
+    source_map
+        .documents()
+        .iter()
+        .map(|id| {
+            let keys = documents.remove(id.as_str()).unwrap_or_default();
+            Document {
+                id: Some(id.clone()),
+                resource: Resource::from_keys(&keys),
+                prefix: Some(KeyPath::document(id)),
+                keys,
+            }
+        })
+        .collect()
+}
+
+/// Re-key a single-resource file so its keys carry the resource identity, the way
+/// they would in a multi-document file. Diffs then match resources by identity,
+/// even when a manifest gains or loses documents or they are reordered.
+pub fn key_by_resource(map: &FlatMap) -> FlatMap {
+    if is_multi_document(map) {
+        return map.clone();
+    }
+    match Resource::from_keys(map) {
+        Some(resource) => {
+            let id = resource.identity();
+            map.iter()
+                .map(|(key, value)| (prefixed(&id, key), value.clone()))
+                .collect()
+        }
+        None => map.clone(),
+    }
+}
+
+/// Whether every key carries a document prefix.
+fn is_multi_document(map: &FlatMap) -> bool {
+    !map.is_empty() && map.keys().all(|k| split_document(k).is_some())
+}
+
+pub(super) fn prefixed(id: &str, key: &str) -> String {
+    let separator = if key.is_empty() || key.starts_with('[') {
+        ""
+    } else {
+        "."
+    };
+    format!("[{}]{}{}", id, separator, key)
+}
@@ -0,0 +1,175 @@
+use anyhow::{Context, Result, bail};
+use serde_json::{Map, Value};
+
+/// Parse a dotenv file into a flat object of string values.
+///
+/// Supports `KEY=VALUE` lines with an optional `export` prefix, `#` comments
+/// (whole-line, or after whitespace in unquoted values), and single, double
+/// or backtick quoted values, which may span several lines.
+/// Double-quoted values understand `\n`, `\r`, `\t`, `\"`, `\\` and `\$`.
+/// `${VAR}` references are kept as written, not expanded. A repeated key keeps its last value.
+pub fn parse(content: &str) -> Result<Value> {
+    let mut vars = Map::new();
+    let mut lines = content.lines().enumerate();
+
+    while let Some((index, line)) = lines.next() {
+        let line_num = index + 1;
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+
+        let assignment = trimmed
+            .strip_prefix("export ")
+            .map(str::trim_start)
+            .unwrap_or(trimmed);
+        let (key, rest) = assignment
+            .split_once('=')
+            .with_context(|| format!("line {}: expected KEY=VALUE", line_num))?;
+        let key = key.trim();
+        if !is_valid_key(key) {
+            bail!("line {}: invalid key '{}'", line_num, key);
+        }
+
+        let quoted = rest.trim_start();
+        let value = match quoted.chars().next() {
+            Some(quote @ ('"' | '\'' | '`')) => {
+                let mut raw = quoted[1..].to_string();
+                let end = loop {
+                    if let Some(end) = closing_quote(&raw, quote) {
+                        break end;
+                    }
+                    let Some((_, next)) = lines.next() else {
+                        bail!("line {}: unterminated {} quote", line_num, quote);
+                    };
+                    raw.push('\n');
+                    raw.push_str(next);
+                };
+                let tail = raw[end + 1..].trim();
+                if !tail.is_empty() && !tail.starts_with('#') {
+                    bail!("line {}: unexpected text after closing quote", line_num);
+                }
+                if quote == '"' {
+                    unescape(&raw[..end])
+                } else {
+                    raw[..end].to_string()
+                }
+            }
+            _ => strip_inline_comment(rest).trim().to_string(),
+        };
+        vars.insert(key.to_string(), Value::String(value));
+    }
+
+    Ok(Value::Object(vars))
+}
+
+fn is_valid_key(key: &str) -> bool {
+    let mut chars = key.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'))
+}
+
+/// Byte offset of the quote closing a value; backslash escapes only apply in double quotes.
+fn closing_quote(s: &str, quote: char) -> Option<usize> {
+    let mut escaped = false;
+    for (i, c) in s.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' && quote == '"' {
+            escaped = true;
+        } else if c == quote {
+            return Some(i);
+        }
+    }
+    None
+}
+
+fn unescape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some('t') => out.push('\t'),
+            Some(c @ ('"' | '\\' | '$')) => out.push(c),
+            Some(c) => {
+                out.push('\\');
+                out.push(c);
+            }
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+/// Drop a `#` comment from an unquoted value. The `#` must follow whitespace,
+/// so values such as `color=#fff` or `url=http://host/#frag` stay intact.
+fn strip_inline_comment(value: &str) -> &str {
+    let mut prev_ws = false;
+    for (i, c) in value.char_indices() {
+        if c == '#' && prev_ws {
+            return &value[..i];
+        }
+        prev_ws = c.is_whitespace();
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get<'a>(value: &'a Value, key: &str) -> &'a str {
+        value[key].as_str().unwrap()
+    }
+
+    #[test]
+    fn test_parse_dotenv() {
+        let content = r#"
+# database settings
+export DB_HOST = db.internal
+DB_PORT=5432 # default port
+COLOR=#fff
+EMPTY=
+COMMENTED= # nothing here
+SINGLE='literal \n $HOME'
+DOUBLE="line1\nsay \"hi\""
+URL=${BASE_URL}/api
+"#;
+        let value = parse(content).unwrap();
+        assert_eq!(get(&value, "DB_HOST"), "db.internal");
+        assert_eq!(get(&value, "DB_PORT"), "5432");
+        assert_eq!(get(&value, "COLOR"), "#fff");
+        assert_eq!(get(&value, "EMPTY"), "");
+        assert_eq!(get(&value, "COMMENTED"), "");
+        assert_eq!(get(&value, "SINGLE"), "literal \\n $HOME");
+        assert_eq!(get(&value, "DOUBLE"), "line1\nsay \"hi\"");
+        assert_eq!(get(&value, "URL"), "${BASE_URL}/api");
+    }
+
+    #[test]
+    fn test_parse_dotenv_multiline() {
+        let content = "KEY=\"-----BEGIN KEY-----\nabc\n-----END KEY-----\" # pem\nNEXT=1\n";
+        let value = parse(content).unwrap();
+        assert_eq!(
+            get(&value, "KEY"),
+            "-----BEGIN KEY-----\nabc\n-----END KEY-----"
+        );
+        assert_eq!(get(&value, "NEXT"), "1");
+    }
+
+    #[test]
+    fn test_parse_dotenv_errors() {
+        assert!(parse("JUST_A_WORD\n").is_err());
+        assert!(parse("1BAD=value\n").is_err());
+        assert!(parse("KEY=\"never closed\nOTHER=1\n").is_err());
+        assert!(parse("KEY='a' trailing\n").is_err());
+    }
+}
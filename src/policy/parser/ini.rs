@@ -0,0 +1,193 @@
+use anyhow::{Result, bail};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+use super::SourceMap;
+use crate::keypath::join_key;
+use crate::models::Span;
+
+/// Parse an INI-style file (`.ini`, `.cfg`) into a flat object.
+///
+/// Keys inside a `[section]` are prefixed with the section name (`section.key`),
+/// and keys before the first section stay top-level. Both `key = value` and
+/// `key: value` are accepted; a bare `key` gets an empty value. Lines starting
+/// with `;` or `#` are comments, as is the rest of an unquoted value after ` ;` or ` #`.
+/// As in Python's configparser, a line indented deeper than the key that opened the
+/// value, and without `=` or `:`, continues that value; other indented lines are keys.
+/// Continuation lines are joined with `\n` as configparser does, so rewrapping a value
+/// changes it. Surrounding quotes are removed, and a repeated key keeps its last value.
+/// A key with brackets (PHP's `extension[]`) is quoted like any other key path.
+pub fn parse(content: &str) -> Result<Value> {
+    parse_with_spans(content).map(|(values, _)| values)
+}
+
+/// Line and column of each key and section header. Content that does not parse has none.
+pub fn spans(content: &str) -> SourceMap {
+    parse_with_spans(content)
+        .map(|(_, spans)| SourceMap::from(spans))
+        .unwrap_or_default()
+}
+
+fn parse_with_spans(content: &str) -> Result<(Value, HashMap<String, Span>)> {
+    let mut values = Map::new();
+    let mut spans = HashMap::new();
+    let mut section = String::new();
+    // Key of the last assignment and its indentation, for continuation lines
+    let mut last_key: Option<(String, usize)> = None;
+
+    for (index, line) in content.lines().enumerate() {
+        let line_num = index + 1;
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with(';') || trimmed.starts_with('#') {
+            continue;
+        }
+        let indent = line.chars().take_while(|c| c.is_whitespace()).count();
+        let span = Span {
+            line: line_num,
+            column: indent + 1,
+        };
+
+        if let Some((key, key_indent)) = &last_key
+            && indent > *key_indent
+            && !trimmed.contains(['=', ':'])
+            && let Some(Value::String(value)) = values.get_mut(key)
+        {
+            value.push('\n');
+            value.push_str(strip_inline_comment(trimmed).trim_end());
+            continue;
+        }
+
+        if let Some(header) = trimmed.strip_prefix('[') {
+            let Some(name) = header.strip_suffix(']') else {
+                bail!("line {}: unterminated section header", line_num);
+            };
+            section = name.trim().to_string();
+            spans.insert(section.clone(), span);
+            last_key = None;
+            continue;
+        }
+
+        let (key, value) = match trimmed.find(['=', ':']) {
+            Some(i) => (trimmed[..i].trim(), trimmed[i + 1..].trim()),
+            None => (strip_inline_comment(trimmed).trim(), ""),
+        };
+        if key.is_empty() {
+            bail!("line {}: missing key", line_num);
+        }
+
+        let full_key = join_ini_key(&section, key);
+        values.insert(full_key.clone(), Value::String(unquote(value)));
+        spans.insert(full_key.clone(), span);
+        last_key = Some((full_key, indent));
+    }
+
+    Ok((Value::Object(values), spans))
+}
+
+/// `section.key` as written, so dotted keys stay nested paths, unless the key
+/// would not read back as one: `PHP["extension[]"]`.
+fn join_ini_key(section: &str, key: &str) -> String {
+    if key.contains(['[', ']']) || (section.is_empty() && key.starts_with('/')) {
+        join_key(section, key)
+    } else if section.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", section, key)
+    }
+}
+
+/// Remove matching surrounding quotes, or an inline comment from an unquoted value.
+fn unquote(value: &str) -> String {
+    for quote in ['"', '\''] {
+        if value.len() >= 2 && value.starts_with(quote) && value.ends_with(quote) {
+            return value[1..value.len() - 1].to_string();
+        }
+    }
+    strip_inline_comment(value).trim_end().to_string()
+}
+
+/// Drop a `;` or `#` comment that follows whitespace.
+fn strip_inline_comment(value: &str) -> &str {
+    let mut prev_ws = false;
+    for (i, c) in value.char_indices() {
+        if matches!(c, ';' | '#') && prev_ws {
+            return &value[..i];
+        }
+        prev_ws = c.is_whitespace();
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ini() {
+        let content = r#"
+; global settings
+name = legacy-billing
+
+[logging]
+level = DEBUG ; noisy
+format: "%(asctime)s %(message)s"
+
+[database]
+host=db.internal
+skip-networking
+description = first line
+  second line
+"#;
+        let value = parse(content).unwrap();
+        assert_eq!(value["name"], "legacy-billing");
+        assert_eq!(value["logging.level"], "DEBUG");
+        assert_eq!(value["logging.format"], "%(asctime)s %(message)s");
+        assert_eq!(value["database.host"], "db.internal");
+        assert_eq!(value["database.skip-networking"], "");
+        assert_eq!(value["database.description"], "first line\nsecond line");
+    }
+
+    #[test]
+    fn test_parse_ini_indented_keys() {
+        let content = "[metadata]\n    name = billing\n    version = 1.0\n    description =\n        first line\n        second line\n    license = MIT\n";
+        let value = parse(content).unwrap();
+        assert_eq!(value["metadata.name"], "billing");
+        assert_eq!(value["metadata.version"], "1.0");
+        assert_eq!(value["metadata.description"], "\nfirst line\nsecond line");
+        assert_eq!(value["metadata.license"], "MIT");
+    }
+
+    #[test]
+    fn test_parse_ini_bracketed_keys() {
+        let content = "[PHP]\nextension[] = curl\nextension[] = gd\n";
+        let value = parse(content).unwrap();
+        assert_eq!(value[r#"PHP["extension[]"]"#], "gd");
+        for key in value.as_object().unwrap().keys() {
+            assert_eq!(
+                crate::keypath::KeyPath::parse(key).unwrap().to_string(),
+                *key
+            );
+        }
+    }
+
+    #[test]
+    fn test_ini_spans() {
+        let spans = spans("top = 1\n\n[logging]\n  level = DEBUG\n");
+        let at = |key: &str| {
+            spans
+                .span(&key.parse().unwrap())
+                .map(|s| (s.line, s.column))
+        };
+        assert_eq!(at("top"), Some((1, 1)));
+        assert_eq!(at("logging"), Some((3, 1)));
+        assert_eq!(at("logging.level"), Some((4, 3)));
+        assert_eq!(at("logging.format"), Some((3, 1)));
+    }
+
+    #[test]
+    fn test_parse_ini_errors() {
+        let err = parse("[ok]\na=1\n[broken\n").unwrap_err();
+        assert!(err.to_string().contains("line 3"));
+        assert!(parse("= value\n").is_err());
+    }
+}
@@ -0,0 +1,555 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::format::ConfigFormat;
+use crate::keypath::{join_index, join_key};
+use crate::models::FlatValue;
+
+mod documents;
+mod dotenv;
+mod ini;
+mod properties;
+mod spans;
+mod xml;
+mod yaml;
+
+pub use documents::{Document, key_by_resource, split_documents};
+pub use spans::SourceMap;
+
+/// A file's flattened keys and their typed values.
+pub type FlatMap = HashMap<String, FlatValue>;
+
+/// Parse config content from a string into a flat key-value map,
+/// picking the parser from the file name at `path`, or the content when it has no extension.
+/// Used by git integration to parse in-memory content from git blobs.
+pub fn parse_config_content(content: &str, path: &Path) -> Result<FlatMap> {
+    let format = ConfigFormat::detect(path, content.as_bytes())
+        .with_context(|| format!("Unsupported config format: {}", path.display()))?;
+    let yaml::LastKeyWins(value) = match format {
+        ConfigFormat::Yaml => {
+            let mut documents = parse_yaml_documents(content)?;
+            if documents.len() > 1 {
+                return Ok(documents::flatten_documents(&documents));
+            }
+            yaml::LastKeyWins(documents.pop().unwrap_or(serde_yaml::Value::Null))
+        }
+        ConfigFormat::Json => match serde_json::from_str(content) {
+            Ok(value) => value,
+            // Comments and trailing commas (`tsconfig.json`, VS Code settings) are
+            // JSONC, which JSON5 accepts. Report the strict error if that fails too.
+            Err(e) => json5::from_str(content)
+                .map_err(|_| e)
+                .context("Failed to parse JSON content")?,
+        },
+        ConfigFormat::Json5 => json5::from_str(content).context("Failed to parse JSON5 content")?,
+        ConfigFormat::Toml => toml::from_str(content).context("Failed to parse TOML content")?,
+        ConfigFormat::Dotenv => {
+            let values = dotenv::parse(content).context("Failed to parse dotenv content")?;
+            return Ok(flat_keys(values));
+        }
+        ConfigFormat::Ini => {
+            let values = ini::parse(content).context("Failed to parse INI content")?;
+            return Ok(flat_keys(values));
+        }
+        ConfigFormat::Properties => {
+            let values =
+                properties::parse(content).context("Failed to parse properties content")?;
+            return Ok(flat_keys(values));
+        }
+        // Blocks nest by type and labels (`resource.aws_instance.web.ami`), and
+        // expressions that are not literals are kept as `${...}` strings
+        ConfigFormat::Hcl => hcl::from_str(content).context("Failed to parse HCL content")?,
+        // Attributes are `@name` keys and repeated elements become lists
+        ConfigFormat::Xml => {
+            let value = xml::parse(content).context("Failed to parse XML content")?;
+            yaml::LastKeyWins::deserialize(value)?
+        }
+    };
+
+    let mut map = HashMap::new();
+    flatten("", &value, &mut map);
+    Ok(map)
+}
+
+/// Line and column of each flattened key in YAML, JSON (and JSON5), TOML, INI and properties
+/// content, keyed like `parse_config_content`. Other formats, and content that does not parse,
+/// have none.
+pub fn parse_config_source_map(content: &str, path: &Path) -> SourceMap {
+    match ConfigFormat::detect(path, content.as_bytes()) {
+        Some(ConfigFormat::Yaml) => {
+            let Ok(documents) = parse_yaml_documents(content) else {
+                return SourceMap::default();
+            };
+            let mut maps = yaml::yaml_source_maps(content);
+            if documents.len() > 1 {
+                let ids: Vec<String> = documents::document_ids(&documents)
+                    .into_iter()
+                    .map(|(id, _)| id)
+                    .collect();
+                let mut source_map = SourceMap::default();
+                if maps.len() == documents.len() {
+                    for (id, map) in ids.iter().zip(maps) {
+                        source_map.extend(map.prefixed(id));
+                    }
+                }
+                source_map.set_documents(ids);
+                return source_map;
+            }
+            maps.pop().unwrap_or_default()
+        }
+        Some(ConfigFormat::Json | ConfigFormat::Json5) => spans::json_spans(content),
+        Some(ConfigFormat::Toml) => spans::toml_spans(content),
+        Some(ConfigFormat::Ini) => ini::spans(content),
+        Some(ConfigFormat::Properties) => properties::spans(content),
+        _ => SourceMap::default(),
+    }
+}
+
+/// Parse every document of a YAML stream, skipping empty ones (e.g. after a trailing `---`).
+/// Merge keys (`<<: *defaults`) are resolved, and a repeated key keeps its last value.
+fn parse_yaml_documents(content: &str) -> Result<Vec<serde_yaml::Value>> {
+    let mut documents = Vec::new();
+    for document in serde_yaml::Deserializer::from_str(content) {
+        let yaml::LastKeyWins(mut value) =
+            yaml::LastKeyWins::deserialize(document).context("Failed to parse YAML content")?;
+        if !value.is_null() {
+            yaml::resolve_merges(&mut value).context("Failed to parse YAML content")?;
+            documents.push(value);
+        }
+    }
+    Ok(documents)
+}
+
+/// Parse a config file (YAML/JSON/JSON5/TOML/dotenv/INI/properties/HCL/XML) into a flat key-value map.
+/// Nested keys are joined with dots: `database.host = "localhost"`
+pub fn parse_config_file(path: &Path) -> Result<FlatMap> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+    parse_config_content(&content, path)
+        .with_context(|| format!("Failed to parse: {}", path.display()))
+}
+
+/// Keys of dotenv, INI and properties files are dotted paths already (`logging.level`),
+/// so they are used as written rather than quoted like a key containing dots.
+fn flat_keys(values: serde_json::Value) -> FlatMap {
+    let serde_json::Value::Object(values) = values else {
+        return FlatMap::new();
+    };
+    values
+        .into_iter()
+        .filter_map(|(key, value)| Some((key, FlatValue::from_json(&value)?)))
+        .collect()
+}
+
+/// Recursively flatten a parsed value into dot-notation keys, keeping scalar types.
+/// Keys containing dots or brackets are quoted (`labels["app.kubernetes.io/name"]`), see `KeyPath`.
+/// Empty maps and lists below the top level are kept as `{}` and `[]` markers, and a
+/// tagged YAML value (`!Ref db`) is nested under its tag (`!Ref`).
+fn flatten(prefix: &str, value: &serde_yaml::Value, map: &mut FlatMap) {
+    match value {
+        serde_yaml::Value::Mapping(obj) if !obj.is_empty() || prefix.is_empty() => {
+            for (k, v) in obj {
+                flatten(&join_key(prefix, &map_key(k)), v, map);
+            }
+        }
+        serde_yaml::Value::Sequence(arr) if !arr.is_empty() || prefix.is_empty() => {
+            for (i, v) in arr.iter().enumerate() {
+                flatten(&join_index(prefix, i), v, map);
+            }
+        }
+        serde_yaml::Value::Tagged(tagged) => {
+            flatten(
+                &join_key(prefix, &tagged.tag.to_string()),
+                &tagged.value,
+                map,
+            );
+        }
+        scalar => {
+            if let Some(value) = FlatValue::from_yaml(scalar) {
+                map.insert(prefix.to_string(), value);
+            }
+        }
+    }
+}
+
+/// A map key as written in flattened keys; numbers and booleans are written as text.
+fn map_key(key: &serde_yaml::Value) -> String {
+    match key {
+        serde_yaml::Value::String(s) => s.clone(),
+        serde_yaml::Value::Number(n) => n.to_string(),
+        serde_yaml::Value::Bool(b) => b.to_string(),
+        // `LastKeyWins` rejects other keys
+        other => serde_yaml::to_string(other)
+            .map(|s| s.trim_end().to_string())
+            .unwrap_or_default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keypath::KeyPath;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    fn key(path: &str) -> KeyPath {
+        KeyPath::parse(path).unwrap()
+    }
+
+    fn write_temp_file(content: &str, extension: &str) -> NamedTempFile {
+        let mut file = tempfile::Builder::new()
+            .suffix(&format!(".{}", extension))
+            .tempfile()
+            .unwrap();
+        file.write_all(content.as_bytes()).unwrap();
+        file
+    }
+
+    #[test]
+    fn test_flatten_yaml() {
+        let file = write_temp_file("database:\n  host: localhost\n  port: 5432\n", "yaml");
+        let map = parse_config_file(file.path()).unwrap();
+        assert_eq!(map["database.host"], FlatValue::from("localhost"));
+        assert_eq!(map["database.port"], FlatValue::Int(5432));
+    }
+
+    #[test]
+    fn test_flatten_json() {
+        let file = write_temp_file(r#"{"app": {"name": "test", "debug": true}}"#, "json");
+        let map = parse_config_file(file.path()).unwrap();
+        assert_eq!(map["app.name"], FlatValue::from("test"));
+        assert_eq!(map["app.debug"], FlatValue::Bool(true));
+    }
+
+    #[test]
+    fn test_flatten_toml() {
+        let file = write_temp_file("[server]\nname = \"prod\"\nport = 8080\n", "toml");
+        let map = parse_config_file(file.path()).unwrap();
+        assert_eq!(map["server.name"], FlatValue::from("prod"));
+        assert_eq!(map["server.port"], FlatValue::Int(8080));
+    }
+
+    #[test]
+    fn test_flatten_jsonc() {
+        let content = r#"{
+  // Base options
+  "compilerOptions": {
+    "strict": true,
+    "target": "es2022", /* trailing comma next */
+  },
+}"#;
+        let map = parse_config_content(content, Path::new("tsconfig.json")).unwrap();
+        assert_eq!(map["compilerOptions.strict"], FlatValue::Bool(true));
+        assert_eq!(map["compilerOptions.target"], FlatValue::from("es2022"));
+
+        let content = "{port: 8080, name: 'api', ratio: .5}";
+        let map = parse_config_content(content, Path::new("app.json5")).unwrap();
+        assert_eq!(map["port"], FlatValue::Int(8080));
+        assert_eq!(map["name"], FlatValue::from("api"));
+        assert_eq!(map["ratio"], FlatValue::Float(0.5));
+
+        let err = parse_config_content("{\"a\": }", Path::new("broken.json")).unwrap_err();
+        assert!(format!("{:#}", err).contains("line 1"), "{:#}", err);
+    }
+
+    #[test]
+    fn test_flatten_escapes_keys() {
+        let content = r#"{"a.b": 1, "a": {"b": 2}, "x[0]": true, "": null}"#;
+        let map = parse_config_content(content, Path::new("app.json")).unwrap();
+        assert_eq!(map[r#"["a.b"]"#], FlatValue::Int(1));
+        assert_eq!(map["a.b"], FlatValue::Int(2));
+        assert_eq!(map[r#"["x[0]"]"#], FlatValue::Bool(true));
+        assert_eq!(map[r#"[""]"#], FlatValue::Null);
+
+        // Flat formats are already written as paths
+        let map =
+            parse_config_content("logging.level=info\n", Path::new("app.properties")).unwrap();
+        assert_eq!(map["logging.level"], FlatValue::from("info"));
+        let map = parse_config_content("[server]\nport = 80\n", Path::new("app.ini")).unwrap();
+        assert_eq!(map["server.port"], FlatValue::from("80"));
+    }
+
+    #[test]
+    fn test_flatten_deeply_nested() {
+        let file = write_temp_file(r#"{"a": {"b": {"c": {"d": "deep"}}}}"#, "json");
+        let map = parse_config_file(file.path()).unwrap();
+        assert_eq!(map["a.b.c.d"], FlatValue::from("deep"));
+    }
+
+    #[test]
+    fn test_flatten_array() {
+        let file = write_temp_file(r#"{"servers": [{"host": "a"}, {"host": "b"}]}"#, "json");
+        let map = parse_config_file(file.path()).unwrap();
+        assert_eq!(map["servers[0].host"], FlatValue::from("a"));
+        assert_eq!(map["servers[1].host"], FlatValue::from("b"));
+    }
+
+    #[test]
+    fn test_flatten_boolean_and_null() {
+        let file = write_temp_file(
+            r#"{"enabled": true, "disabled": false, "value": null}"#,
+            "json",
+        );
+        let map = parse_config_file(file.path()).unwrap();
+        assert_eq!(map["enabled"], FlatValue::Bool(true));
+        assert_eq!(map["disabled"], FlatValue::Bool(false));
+        assert_eq!(map["value"], FlatValue::Null);
+    }
+
+    #[test]
+    fn test_flatten_keeps_types() {
+        let content = r#"{"port": 5432, "quoted": "5432", "ratio": 0.5, "tags": [], "extra": {}}"#;
+        let map = parse_config_content(content, Path::new("app.json")).unwrap();
+        assert_eq!(map["port"], FlatValue::Int(5432));
+        assert_eq!(map["quoted"], FlatValue::from("5432"));
+        assert_eq!(map["ratio"], FlatValue::Float(0.5));
+        assert_eq!(map["tags"], FlatValue::EmptyArray);
+        assert_eq!(map["extra"], FlatValue::EmptyObject);
+        assert_eq!(map["port"].to_string(), map["quoted"].to_string());
+    }
+
+    #[test]
+    fn test_flatten_keeps_non_finite_and_unsigned_numbers() {
+        let yaml = "nan: .nan\ninf: .inf\nneg: -.inf\nbig: 18446744073709551615\nsmall: -5\n";
+        let map = parse_config_content(yaml, Path::new("app.yaml")).unwrap();
+        assert!(matches!(map["nan"], FlatValue::Float(f) if f.is_nan()));
+        assert_eq!(map["inf"], FlatValue::Float(f64::INFINITY));
+        assert_eq!(map["neg"], FlatValue::Float(f64::NEG_INFINITY));
+        assert_eq!(map["big"], FlatValue::UInt(u64::MAX));
+        assert_eq!(map["small"], FlatValue::Int(-5));
+        assert_eq!(map["nan"].to_string(), ".nan");
+        assert_eq!(map["big"].to_string(), "18446744073709551615");
+
+        let json5 = "{a: NaN, b: -Infinity, c: 18446744073709551615}";
+        let map = parse_config_content(json5, Path::new("app.json5")).unwrap();
+        assert_eq!(map["a"], FlatValue::Float(f64::NAN));
+        assert_eq!(map["b"], FlatValue::Float(f64::NEG_INFINITY));
+        assert_eq!(map["c"], FlatValue::UInt(u64::MAX));
+
+        let toml = "limit = inf\nratio = nan\n";
+        let map = parse_config_content(toml, Path::new("app.toml")).unwrap();
+        assert_eq!(map["limit"], FlatValue::Float(f64::INFINITY));
+        assert_eq!(map["ratio"], FlatValue::Float(f64::NAN));
+
+        // Snapshots write non-finite floats explicitly and read them back
+        let json = serde_json::to_string(&FlatValue::Float(f64::NEG_INFINITY)).unwrap();
+        assert_eq!(json, r#"{"$float":"-.inf"}"#);
+        for value in [
+            FlatValue::Float(f64::NAN),
+            FlatValue::Float(f64::INFINITY),
+            FlatValue::UInt(u64::MAX),
+        ] {
+            let json = serde_json::to_string(&value).unwrap();
+            assert_eq!(serde_json::from_str::<FlatValue>(&json).unwrap(), value);
+        }
+        assert!(serde_json::from_str::<FlatValue>(r#"{"$float": "1.5"}"#).is_err());
+    }
+
+    #[test]
+    fn test_flatten_hcl() {
+        let content = r#"
+region = "eu-west-1"
+azs    = ["eu-west-1a", "eu-west-1b"]
+db_password = var.db_password
+
+resource "aws_instance" "web" {
+  instance_type = "t3.micro"
+  monitoring    = true
+}
+"#;
+        let map = parse_config_content(content, Path::new("prod.tfvars")).unwrap();
+        assert_eq!(map["region"], FlatValue::from("eu-west-1"));
+        assert_eq!(map["azs[1]"], FlatValue::from("eu-west-1b"));
+        assert_eq!(map["db_password"], FlatValue::from("${var.db_password}"));
+        assert_eq!(
+            map["resource.aws_instance.web.instance_type"],
+            FlatValue::from("t3.micro")
+        );
+        assert_eq!(
+            map["resource.aws_instance.web.monitoring"],
+            FlatValue::Bool(true)
+        );
+    }
+
+    #[test]
+    fn test_flatten_xml() {
+        let content = r#"<Server port="8005">
+  <Service name="Catalina">
+    <Connector port="8080" protocol="HTTP/1.1"/>
+    <Connector port="8443" SSLEnabled="true"/>
+    <Engine>localhost</Engine>
+  </Service>
+</Server>"#;
+        let map = parse_config_content(content, Path::new("conf/server.xml")).unwrap();
+        assert_eq!(map["Server.@port"], FlatValue::from("8005"));
+        assert_eq!(map["Server.Service.@name"], FlatValue::from("Catalina"));
+        assert_eq!(
+            map["Server.Service.Connector[1].@SSLEnabled"],
+            FlatValue::from("true")
+        );
+        assert_eq!(map["Server.Service.Engine"], FlatValue::from("localhost"));
+    }
+
+    #[test]
+    fn test_multi_document_yaml() {
+        let content = r#"
+apiVersion: apps/v1
+kind: Deployment
+metadata:
+  name: api
+  namespace: prod
+spec:
+  replicas: 3
+---
+plain: value
+---
+apiVersion: v1
+kind: Namespace
+metadata:
+  name: prod
+---
+"#;
+        let map = parse_config_content(content, Path::new("bundle.yaml")).unwrap();
+        assert_eq!(
+            map["[apps/v1/Deployment/prod/api].spec.replicas"],
+            FlatValue::Int(3)
+        );
+        assert_eq!(map["[1].plain"], FlatValue::from("value"));
+        assert_eq!(
+            map["[v1/Namespace/prod].kind"],
+            FlatValue::from("Namespace")
+        );
+
+        let spans = parse_config_source_map(content, Path::new("bundle.yaml"));
+        let documents = split_documents(&map, &spans);
+        let ids: Vec<_> = documents.iter().map(|d| d.id.as_deref().unwrap()).collect();
+        assert_eq!(
+            ids,
+            vec!["apps/v1/Deployment/prod/api", "1", "v1/Namespace/prod"]
+        );
+        let deployment = documents[0].resource.as_ref().unwrap();
+        assert_eq!(deployment.kind, "Deployment");
+        assert_eq!(deployment.namespace.as_deref(), Some("prod"));
+        assert_eq!(documents[0].keys["spec.replicas"], FlatValue::Int(3));
+        assert!(documents[1].resource.is_none());
+    }
+
+    #[test]
+    fn test_documents_keep_file_order() {
+        let content = (0..12)
+            .map(|i| format!("n: {}\n", i))
+            .collect::<Vec<_>>()
+            .join("---\n");
+        let path = Path::new("many.yaml");
+        let map = parse_config_content(&content, path).unwrap();
+        let documents = split_documents(&map, &parse_config_source_map(&content, path));
+        let ids: Vec<_> = documents.iter().map(|d| d.id.clone().unwrap()).collect();
+        let expected: Vec<_> = (0..12).map(|i| i.to_string()).collect();
+        assert_eq!(ids, expected);
+        assert_eq!(documents[10].keys["n"], FlatValue::Int(10));
+    }
+
+    #[test]
+    fn test_top_level_list_is_one_document() {
+        let content = r#"[{"name": "a"}, {"name": "b"}]"#;
+        let path = Path::new("items.json");
+        let map = parse_config_content(content, path).unwrap();
+        let documents = split_documents(&map, &parse_config_source_map(content, path));
+        assert_eq!(documents.len(), 1);
+        assert_eq!(documents[0].prefix, None);
+        assert_eq!(documents[0].keys["[1].name"], FlatValue::from("b"));
+    }
+
+    #[test]
+    fn test_single_document_yaml_is_unprefixed() {
+        let content = "---\napiVersion: v1\nkind: ConfigMap\nmetadata:\n  name: app\n";
+        let map = parse_config_content(content, Path::new("cm.yaml")).unwrap();
+        assert_eq!(map["metadata.name"], FlatValue::from("app"));
+
+        let documents = split_documents(
+            &map,
+            &parse_config_source_map(content, Path::new("cm.yaml")),
+        );
+        assert_eq!(documents.len(), 1);
+        assert_eq!(documents[0].id.as_deref(), Some("v1/ConfigMap/app"));
+        assert_eq!(documents[0].prefix, None);
+
+        let keyed = key_by_resource(&map);
+        assert_eq!(
+            keyed["[v1/ConfigMap/app].metadata.name"],
+            FlatValue::from("app")
+        );
+    }
+
+    #[test]
+    fn test_multi_document_spans() {
+        let content =
+            "apiVersion: v1\nkind: Namespace\nmetadata:\n  name: prod\n---\nplain: value\n";
+        let spans = parse_config_source_map(content, Path::new("bundle.yaml"));
+        let span = spans
+            .span(&key("[v1/Namespace/prod].metadata.name"))
+            .unwrap();
+        assert_eq!((span.line, span.column), (4, 3));
+        let span = spans.span(&key("[1].plain")).unwrap();
+        assert_eq!((span.line, span.column), (6, 1));
+        assert_eq!(
+            parse_config_source_map("{", Path::new("broken.json")),
+            SourceMap::default()
+        );
+    }
+
+    #[test]
+    fn test_yaml_merge_keys() {
+        let content = r#"
+x-defaults: &defaults
+  image: nginx
+  restart: always
+web:
+  <<: *defaults
+  restart: never
+"#;
+        let map = parse_config_content(content, Path::new("compose.yaml")).unwrap();
+        assert_eq!(map["web.image"], FlatValue::from("nginx"));
+        assert_eq!(map["web.restart"], FlatValue::from("never"));
+        assert!(!map.keys().any(|k| k.contains("<<")));
+
+        let source_map = parse_config_source_map(content, Path::new("compose.yaml"));
+        assert_eq!(source_map.span(&key("web.image")).unwrap().line, 6);
+        let origin = source_map.origin(&key("web.image")).unwrap();
+        assert_eq!((origin.anchor.as_str(), origin.span.line), ("defaults", 3));
+        assert!(source_map.origin(&key("web.restart")).is_none());
+    }
+
+    #[test]
+    fn test_yaml_duplicate_keys() {
+        let content = "port: 80\nport: 8080\n---\nname: a\nname: b\n";
+        let path = Path::new("app.yaml");
+        let map = parse_config_content(content, path).unwrap();
+        assert_eq!(map["[0].port"], FlatValue::Int(8080));
+        assert_eq!(map["[1].name"], FlatValue::from("b"));
+
+        let source_map = parse_config_source_map(content, path);
+        let keys: Vec<_> = source_map
+            .duplicates()
+            .iter()
+            .map(|d| (d.key.to_string(), d.span.line))
+            .collect();
+        assert_eq!(
+            keys,
+            [("[0].port".to_string(), 2), ("[1].name".to_string(), 5)]
+        );
+    }
+
+    #[test]
+    fn test_parse_dotenv_content() {
+        let map = parse_config_content(
+            "export API_URL=https://api.internal\nDEBUG=false\n",
+            Path::new("deploy/.env.production"),
+        )
+        .unwrap();
+        assert_eq!(map["API_URL"], FlatValue::from("https://api.internal"));
+        assert_eq!(map["DEBUG"], FlatValue::from("false"));
+    }
+}
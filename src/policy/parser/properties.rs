@@ -0,0 +1,173 @@
+use anyhow::{Result, bail};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+use super::SourceMap;
+use crate::models::Span;
+
+/// Parse a Java `.properties` file into a flat object.
+///
+/// Follows `java.util.Properties`: `#` and `!` start comment lines, the key ends
+/// at the first unescaped `=`, `:` or whitespace, a trailing backslash continues
+/// the logical line, and `\t`, `\n`, `\r`, `\f` and `\uXXXX` escapes are decoded.
+/// Keys are usually dotted already (`logging.level`), so they are kept as written.
+pub fn parse(content: &str) -> Result<Value> {
+    parse_with_spans(content).map(|(values, _)| values)
+}
+
+/// Line and column where each key starts. Content that does not parse has none.
+pub fn spans(content: &str) -> SourceMap {
+    parse_with_spans(content)
+        .map(|(_, spans)| SourceMap::from(spans))
+        .unwrap_or_default()
+}
+
+fn parse_with_spans(content: &str) -> Result<(Value, HashMap<String, Span>)> {
+    let mut values = Map::new();
+    let mut spans = HashMap::new();
+    let mut lines = content.lines().enumerate();
+
+    while let Some((index, line)) = lines.next() {
+        let line_num = index + 1;
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with('!') {
+            continue;
+        }
+
+        // Join continuation lines into one logical line
+        let mut logical = trimmed.to_string();
+        while ends_with_continuation(&logical) {
+            logical.pop();
+            match lines.next() {
+                Some((_, next)) => logical.push_str(next.trim_start()),
+                None => break,
+            }
+        }
+
+        let (key, value) = split_key_value(&logical);
+        let key = unescape(key, line_num)?;
+        let value = unescape(value, line_num)?;
+        let column = line.chars().count() - trimmed.chars().count() + 1;
+        spans.insert(
+            key.clone(),
+            Span {
+                line: line_num,
+                column,
+            },
+        );
+        values.insert(key, Value::String(value));
+    }
+
+    Ok((Value::Object(values), spans))
+}
+
+/// A line continues when it ends with an odd number of backslashes.
+fn ends_with_continuation(line: &str) -> bool {
+    line.chars().rev().take_while(|&c| c == '\\').count() % 2 == 1
+}
+
+/// Split a logical line into its raw (still escaped) key and value.
+fn split_key_value(line: &str) -> (&str, &str) {
+    let mut escaped = false;
+    let mut key_end = line.len();
+    for (i, c) in line.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if matches!(c, '=' | ':') || c.is_whitespace() {
+            key_end = i;
+            break;
+        }
+    }
+
+    let rest = line[key_end..].trim_start();
+    let rest = rest
+        .strip_prefix(['=', ':'])
+        .map(str::trim_start)
+        .unwrap_or(rest);
+    (&line[..key_end], rest)
+}
+
+fn unescape(s: &str, line_num: usize) -> Result<String> {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => out.push('\t'),
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some('f') => out.push('\u{c}'),
+            Some('u') => {
+                let hex: String = chars.by_ref().take(4).collect();
+                let decoded = u32::from_str_radix(&hex, 16)
+                    .ok()
+                    .filter(|_| hex.len() == 4)
+                    .and_then(char::from_u32);
+                match decoded {
+                    Some(ch) => out.push(ch),
+                    None => bail!("line {}: invalid \\u escape '\\u{}'", line_num, hex),
+                }
+            }
+            Some(other) => out.push(other),
+            None => {}
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_properties() {
+        let content = r#"
+# Spring settings
+! also a comment
+logging.level=INFO
+server.port : 8080
+app.name    Billing Service
+app.greeting = Hello\tWorld \u00e9
+path\ with\ spaces = C:\\data
+app.list = one, \
+           two, \
+           three
+empty.value =
+"#;
+        let value = parse(content).unwrap();
+        assert_eq!(value["logging.level"], "INFO");
+        assert_eq!(value["server.port"], "8080");
+        assert_eq!(value["app.name"], "Billing Service");
+        assert_eq!(value["app.greeting"], "Hello\tWorld é");
+        assert_eq!(value["path with spaces"], "C:\\data");
+        assert_eq!(value["app.list"], "one, two, three");
+        assert_eq!(value["empty.value"], "");
+    }
+
+    #[test]
+    fn test_properties_spans() {
+        let spans = spans(
+            "# comment\nlogging.level=INFO\napp.list = one, \\\n    two\n  server.port: 8080\n",
+        );
+        let at = |key: &str| {
+            spans
+                .span(&key.parse().unwrap())
+                .map(|s| (s.line, s.column))
+        };
+        assert_eq!(at("logging.level"), Some((2, 1)));
+        assert_eq!(at("app.list"), Some((3, 1)));
+        assert_eq!(at("server.port"), Some((5, 3)));
+        assert_eq!(at("server.host"), None);
+    }
+
+    #[test]
+    fn test_parse_properties_invalid_escape() {
+        let err = parse("a=1\nb=\\uZZZZ\n").unwrap_err();
+        assert!(err.to_string().contains("line 2"));
+    }
+}
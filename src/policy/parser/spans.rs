@@ -0,0 +1,467 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::iter::Peekable;
+use std::str::Chars;
+
+use toml::Spanned;
+use toml::de::{DeTable, DeValue};
+
+use crate::keypath::{KeyPath, join_index, join_key};
+use crate::models::{DuplicateKey, Origin, Span};
+
+type Spans = HashMap<String, Span>;
+
+/// Location of every flattened key (and of the maps and lists above it), by key.
+/// YAML keys reached through an alias or a merge key point at where the anchor is
+/// used, and also record the anchor that holds their value.
+/// For a YAML stream of several documents it also lists the documents in file order.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SourceMap {
+    spans: Spans,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    origins: HashMap<String, Origin>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    duplicates: Vec<DuplicateKey>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    documents: Vec<String>,
+}
+
+impl SourceMap {
+    /// Span of `key`, or of its closest ancestor when the key itself has none
+    /// (e.g. a key that is required but missing).
+    /// A resource prefix added by `key_by_resource` is ignored.
+    pub fn span(&self, key: &KeyPath) -> Option<Span> {
+        self.locate(key).map(|found| self.spans[&found])
+    }
+
+    /// Anchor the value of `key` was inherited from, looked up like `span`.
+    pub fn origin(&self, key: &KeyPath) -> Option<&Origin> {
+        self.origins.get(&self.locate(key)?)
+    }
+
+    /// Keys written twice in the same map, in the order they were found.
+    pub fn duplicates(&self) -> &[DuplicateKey] {
+        &self.duplicates
+    }
+
+    /// Ids of the documents of a multi-document file (`[id]` in its keys), in file
+    /// order. Empty for a file with a single document.
+    pub fn documents(&self) -> &[String] {
+        &self.documents
+    }
+
+    /// `key` or the closest ancestor of it that has a span.
+    fn locate(&self, key: &KeyPath) -> Option<String> {
+        let mut current = Some(key.clone());
+        while let Some(path) = current.filter(|p| !p.segments().is_empty()) {
+            let rendered = path.to_string();
+            if self.spans.contains_key(&rendered) {
+                return Some(rendered);
+            }
+            current = path.parent();
+        }
+        self.locate(&key.without_document()?)
+    }
+
+    pub(super) fn insert(&mut self, key: String, span: Span) {
+        self.spans.insert(key, span);
+    }
+
+    pub(super) fn insert_origin(&mut self, key: String, origin: Origin) {
+        self.origins.insert(key, origin);
+    }
+
+    pub(super) fn insert_duplicate(&mut self, duplicate: DuplicateKey) {
+        self.duplicates.push(duplicate);
+    }
+
+    pub(super) fn set_documents(&mut self, ids: Vec<String>) {
+        self.documents = ids;
+    }
+
+    /// The same map with every key moved under a document prefix.
+    pub(super) fn prefixed(self, id: &str) -> SourceMap {
+        SourceMap {
+            spans: self
+                .spans
+                .into_iter()
+                .map(|(key, span)| (super::documents::prefixed(id, &key), span))
+                .collect(),
+            origins: self
+                .origins
+                .into_iter()
+                .map(|(key, origin)| (super::documents::prefixed(id, &key), origin))
+                .collect(),
+            duplicates: self
+                .duplicates
+                .into_iter()
+                .map(|duplicate| DuplicateKey {
+                    key: KeyPath::document(id).join(&duplicate.key),
+                    ..duplicate
+                })
+                .collect(),
+            documents: self.documents,
+        }
+    }
+
+    pub(super) fn extend(&mut self, other: SourceMap) {
+        self.spans.extend(other.spans);
+        self.origins.extend(other.origins);
+        self.duplicates.extend(other.duplicates);
+        self.documents.extend(other.documents);
+    }
+}
+
+impl From<Spans> for SourceMap {
+    fn from(spans: Spans) -> SourceMap {
+        SourceMap {
+            spans,
+            origins: HashMap::new(),
+            duplicates: Vec::new(),
+            documents: Vec::new(),
+        }
+    }
+}
+
+/// Spans of a TOML document. Keys point at the key, array items at the item.
+pub(super) fn toml_spans(content: &str) -> SourceMap {
+    let mut spans = Spans::new();
+    if let Ok(table) = DeTable::parse(content) {
+        let lines = LineIndex::new(content);
+        toml_table_spans("", table.get_ref(), &lines, &mut spans);
+    }
+    spans.into()
+}
+
+fn toml_table_spans(prefix: &str, table: &DeTable<'_>, lines: &LineIndex, spans: &mut Spans) {
+    for (key, value) in table {
+        let path = join_key(prefix, key.get_ref());
+        spans.insert(path.clone(), lines.span(key.span().start));
+        toml_value_spans(&path, value, lines, spans);
+    }
+}
+
+fn toml_value_spans(
+    path: &str,
+    value: &Spanned<DeValue<'_>>,
+    lines: &LineIndex,
+    spans: &mut Spans,
+) {
+    match value.get_ref() {
+        DeValue::Table(table) => toml_table_spans(path, table, lines, spans),
+        DeValue::Array(items) => {
+            for (index, item) in items.iter().enumerate() {
+                let item_path = join_index(path, index);
+                spans.insert(item_path.clone(), lines.span(item.span().start));
+                toml_value_spans(&item_path, item, lines, spans);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Converts byte offsets to line and column.
+struct LineIndex<'a> {
+    content: &'a str,
+    /// Byte offset where each line starts.
+    starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    fn new(content: &'a str) -> LineIndex<'a> {
+        let starts = std::iter::once(0)
+            .chain(content.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        LineIndex { content, starts }
+    }
+
+    fn span(&self, offset: usize) -> Span {
+        let line = self.starts.partition_point(|&start| start <= offset);
+        let start = self.starts[line - 1];
+        let column = self
+            .content
+            .get(start..offset)
+            .map_or(0, |text| text.chars().count());
+        Span {
+            line,
+            column: column + 1,
+        }
+    }
+}
+
+/// Spans of a JSON document. Comments, trailing commas, single quotes and unquoted
+/// keys are accepted, so JSONC and JSON5 files are covered too. Malformed input yields
+/// the spans found before the error; the parser reports the error itself.
+/// A key repeated in an object is recorded as a duplicate, and points at its last occurrence.
+pub(super) fn json_spans(content: &str) -> SourceMap {
+    let mut scanner = JsonScanner {
+        chars: content.chars().peekable(),
+        line: 1,
+        column: 1,
+        spans: Spans::new(),
+        duplicates: Vec::new(),
+    };
+    scanner.value("");
+    let mut source_map = SourceMap::from(scanner.spans);
+    source_map.duplicates = scanner.duplicates;
+    source_map
+}
+
+struct JsonScanner<'a> {
+    chars: Peekable<Chars<'a>>,
+    line: usize,
+    column: usize,
+    spans: Spans,
+    duplicates: Vec<DuplicateKey>,
+}
+
+impl JsonScanner<'_> {
+    fn span(&self) -> Span {
+        Span {
+            line: self.line,
+            column: self.column,
+        }
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    /// Skip whitespace and `//` or `/* */` comments.
+    fn skip_whitespace(&mut self) {
+        loop {
+            match self.chars.peek() {
+                Some(c) if c.is_whitespace() => {
+                    self.bump();
+                }
+                Some('/') => {
+                    self.bump();
+                    match self.bump() {
+                        Some('/') => while self.bump().is_some_and(|c| c != '\n') {},
+                        Some('*') => {
+                            let mut prev = ' ';
+                            while let Some(c) = self.bump() {
+                                if prev == '*' && c == '/' {
+                                    break;
+                                }
+                                prev = c;
+                            }
+                        }
+                        _ => return,
+                    }
+                }
+                _ => return,
+            }
+        }
+    }
+
+    fn value(&mut self, path: &str) -> Option<()> {
+        self.skip_whitespace();
+        match *self.chars.peek()? {
+            '{' => {
+                self.bump();
+                let mut seen: HashMap<String, Span> = HashMap::new();
+                loop {
+                    self.skip_whitespace();
+                    match *self.chars.peek()? {
+                        '}' => {
+                            self.bump();
+                            return Some(());
+                        }
+                        ',' => {
+                            self.bump();
+                        }
+                        c => {
+                            let span = self.span();
+                            let name = match c {
+                                '"' | '\'' => self.string()?,
+                                c if is_identifier(c) => self.identifier(),
+                                _ => return None,
+                            };
+                            let key = join_key(path, &name);
+                            self.skip_whitespace();
+                            if self.bump()? != ':' {
+                                return None;
+                            }
+                            match seen.get(&name) {
+                                Some(&first) => self.duplicates.push(DuplicateKey {
+                                    key: KeyPath::from_flat(&key),
+                                    span,
+                                    first,
+                                }),
+                                None => {
+                                    seen.insert(name, span);
+                                }
+                            }
+                            self.spans.insert(key.clone(), span);
+                            self.value(&key)?;
+                        }
+                    }
+                }
+            }
+            '[' => {
+                self.bump();
+                let mut index = 0;
+                loop {
+                    self.skip_whitespace();
+                    match *self.chars.peek()? {
+                        ']' => {
+                            self.bump();
+                            return Some(());
+                        }
+                        ',' => {
+                            self.bump();
+                        }
+                        _ => {
+                            let item = join_index(path, index);
+                            index += 1;
+                            self.spans.insert(item.clone(), self.span());
+                            self.value(&item)?;
+                        }
+                    }
+                }
+            }
+            '"' | '\'' => self.string().map(drop),
+            _ => {
+                // Numbers, booleans and null run until the next delimiter
+                let mut consumed = false;
+                while let Some(&c) = self.chars.peek() {
+                    if matches!(c, ',' | '}' | ']' | '/') || c.is_whitespace() {
+                        break;
+                    }
+                    self.bump();
+                    consumed = true;
+                }
+                consumed.then_some(())
+            }
+        }
+    }
+
+    /// Read a string literal in double or single quotes, decoding escapes so keys
+    /// match the parsed ones.
+    fn string(&mut self) -> Option<String> {
+        let quote = self.bump()?;
+        let mut out = String::new();
+        loop {
+            match self.bump()? {
+                c if c == quote => return Some(out),
+                '\\' => match self.bump()? {
+                    'n' => out.push('\n'),
+                    't' => out.push('\t'),
+                    'r' => out.push('\r'),
+                    'b' => out.push('\u{8}'),
+                    'f' => out.push('\u{c}'),
+                    'u' => {
+                        let high = self.hex4()?;
+                        let code = if (0xD800..0xDC00).contains(&high) {
+                            // Surrogate pair: a second `\uXXXX` follows
+                            self.bump()?;
+                            self.bump()?;
+                            let low = self.hex4()?;
+                            0x10000 + ((high - 0xD800) << 10) + (low.wrapping_sub(0xDC00) & 0x3FF)
+                        } else {
+                            high
+                        };
+                        out.push(char::from_u32(code).unwrap_or('\u{FFFD}'));
+                    }
+                    // JSON5 line continuation
+                    '\n' => {}
+                    c => out.push(c),
+                },
+                c => out.push(c),
+            }
+        }
+    }
+
+    /// Read an unquoted JSON5 key.
+    fn identifier(&mut self) -> String {
+        let mut name = String::new();
+        while let Some(&c) = self.chars.peek().filter(|&&c| is_identifier(c)) {
+            name.push(c);
+            self.bump();
+        }
+        name
+    }
+
+    fn hex4(&mut self) -> Option<u32> {
+        let mut code = 0;
+        for _ in 0..4 {
+            code = code * 16 + self.bump()?.to_digit(16)?;
+        }
+        Some(code)
+    }
+}
+
+fn is_identifier(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(path: &str) -> KeyPath {
+        KeyPath::parse(path).unwrap()
+    }
+
+    fn at(line: usize, column: usize) -> Option<Span> {
+        Some(Span { line, column })
+    }
+
+    #[test]
+    fn test_json_spans() {
+        let content = "{\n  \"app\": {\"name\": \"a\\\"b\",\n    \"tags\": [1, {\"k\": null}]},\n  \"caf\\u00e9\": true\n}";
+        let spans = json_spans(content);
+        assert_eq!(spans.span(&key("app")), at(2, 3));
+        assert_eq!(spans.span(&key("app.name")), at(2, 11));
+        assert_eq!(spans.span(&key("app.tags[1].k")), at(3, 18));
+        assert_eq!(spans.span(&key("café")), at(4, 3));
+    }
+
+    #[test]
+    fn test_jsonc_spans() {
+        let content = "// settings\n{\n  /* editor */\n  \"editor.tabSize\": 2, // two\n  'quoted': 'a',\n  unquoted: [1, 2,],\n}\n";
+        let spans = json_spans(content);
+        assert_eq!(spans.span(&key(r#"["editor.tabSize"]"#)), at(4, 3));
+        assert_eq!(spans.span(&key("quoted")), at(5, 3));
+        assert_eq!(spans.span(&key("unquoted[1]")), at(6, 17));
+    }
+
+    #[test]
+    fn test_json_duplicate_keys() {
+        let content = "{\n  \"a\": 1,\n  \"b\": {\"c\": 1, \"c\": 2},\n  \"a\": 3\n}";
+        let spans = json_spans(content);
+        let found: Vec<_> = spans
+            .duplicates()
+            .iter()
+            .map(|d| (d.key.to_string(), d.first.line, d.span.line))
+            .collect();
+        assert_eq!(found, [("b.c".to_string(), 3, 3), ("a".to_string(), 2, 4)]);
+        assert_eq!(spans.span(&key("a")), at(4, 3));
+    }
+
+    #[test]
+    fn test_toml_spans() {
+        let content = "title = \"x\"\n\n[server]\nport = 8080\nhosts = [\"a\", \"b\"]\n";
+        let spans = toml_spans(content);
+        assert_eq!(spans.span(&key("title")), at(1, 1));
+        assert_eq!(spans.span(&key("server.port")), at(4, 1));
+        assert_eq!(spans.span(&key("server.hosts[1]")), at(5, 15));
+    }
+
+    #[test]
+    fn test_span_falls_back_to_ancestor() {
+        let spans = json_spans("{\"spec\": {\"replicas\": 3}}");
+        assert_eq!(spans.span(&key("spec.replicas")), at(1, 11));
+        assert_eq!(spans.span(&key("spec.template.image")), at(1, 2));
+        assert_eq!(spans.span(&key("[v1/Pod/web].spec.replicas")), at(1, 11));
+        assert_eq!(spans.span(&key("missing")), None);
+    }
+}
@@ -0,0 +1,180 @@
+use anyhow::{Result, anyhow, bail};
+use quick_xml::XmlVersion;
+use quick_xml::escape::resolve_predefined_entity;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::reader::Reader;
+use serde_json::{Map, Value};
+
+/// Parse an XML file (`web.config`, `server.xml`, `log4j2.xml`, ...) into a nested object.
+///
+/// The root element is the top-level key and child elements nest below it, so
+/// `<Server><Service name="x"/></Server>` has a `Server.Service` key. Attributes are
+/// keys prefixed with `@` (`Server.Service.@name`). The text of an element with
+/// neither attributes nor children is its value; otherwise it is kept under `#text`.
+/// A child element that repeats becomes a list (`appSettings.add[0].@key`), while a
+/// single one does not. Comments, processing instructions and the doctype are
+/// ignored, CDATA is text, and all values are strings.
+pub fn parse(content: &str) -> Result<Value> {
+    let mut reader = Reader::from_str(content);
+    let mut root = Map::new();
+    // Open elements, innermost last
+    let mut stack: Vec<Element> = Vec::new();
+
+    loop {
+        let event = match reader.read_event() {
+            Ok(event) => event,
+            Err(e) => return Err(error_at(content, reader.error_position(), e)),
+        };
+        match event {
+            Event::Start(start) => stack.push(Element::open(&start, content, &reader)?),
+            Event::Empty(start) => {
+                let element = Element::open(&start, content, &reader)?;
+                let parent = stack.last_mut().map_or(&mut root, |p| &mut p.children);
+                element.close(parent);
+            }
+            Event::End(_) => {
+                if let Some(element) = stack.pop() {
+                    let parent = stack.last_mut().map_or(&mut root, |p| &mut p.children);
+                    element.close(parent);
+                }
+            }
+            Event::Text(text) => {
+                if let Some(element) = stack.last_mut() {
+                    element.text.push_str(&text.xml10_content());
+                }
+            }
+            Event::CData(data) => {
+                if let Some(element) = stack.last_mut() {
+                    element.text.push_str(&data.xml10_content());
+                }
+            }
+            Event::GeneralRef(reference) => {
+                let Some(element) = stack.last_mut() else {
+                    continue;
+                };
+                match reference.resolve_char_ref() {
+                    Ok(Some(c)) => element.text.push(c),
+                    Ok(None) => match resolve_predefined_entity(&reference) {
+                        Some(resolved) => element.text.push_str(resolved),
+                        // Entities declared in a doctype are kept as written
+                        None => element.text.push_str(&format!("&{};", &*reference)),
+                    },
+                    Err(e) => return Err(error_at(content, reader.buffer_position(), e)),
+                }
+            }
+            Event::Eof => break,
+            Event::Comment(_) | Event::Decl(_) | Event::PI(_) | Event::DocType(_) => {}
+        }
+    }
+
+    if let Some(element) = stack.last() {
+        bail!("unclosed element <{}>", element.name);
+    }
+    Ok(Value::Object(root))
+}
+
+/// An element whose end tag has not been read yet.
+struct Element {
+    name: String,
+    /// Attributes (`@name`) and child elements.
+    children: Map<String, Value>,
+    text: String,
+}
+
+impl Element {
+    fn open(start: &BytesStart, content: &str, reader: &Reader<&[u8]>) -> Result<Element> {
+        let mut children = Map::new();
+        for attribute in start.attributes() {
+            let attribute =
+                attribute.map_err(|e| error_at(content, reader.buffer_position(), e))?;
+            let value = attribute
+                .normalized_value(XmlVersion::Implicit1_0)
+                .map_err(|e| error_at(content, reader.buffer_position(), e))?;
+            let key = format!("@{}", attribute.key.into_inner());
+            children.insert(key, Value::String(value.into_owned()));
+        }
+        Ok(Element {
+            name: start.name().into_inner().to_string(),
+            children,
+            text: String::new(),
+        })
+    }
+
+    /// Add the finished element to its parent, turning a repeated name into a list.
+    fn close(self, parent: &mut Map<String, Value>) {
+        let text = self.text.trim();
+        let value = if self.children.is_empty() {
+            Value::String(text.to_string())
+        } else {
+            let mut children = self.children;
+            if !text.is_empty() {
+                children.insert("#text".to_string(), Value::String(text.to_string()));
+            }
+            Value::Object(children)
+        };
+        match parent.get_mut(&self.name) {
+            Some(Value::Array(items)) => items.push(value),
+            Some(existing) => *existing = Value::Array(vec![existing.take(), value]),
+            None => {
+                parent.insert(self.name, value);
+            }
+        }
+    }
+}
+
+/// An error with the line of the byte `position` it was found at.
+fn error_at(content: &str, position: u64, error: impl std::fmt::Display) -> anyhow::Error {
+    let end = (position as usize).min(content.len());
+    let line = content.as_bytes()[..end]
+        .iter()
+        .filter(|&&b| b == b'\n')
+        .count()
+        + 1;
+    anyhow!("line {}: {}", line, error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_xml() {
+        let content = r#"<?xml version="1.0" encoding="utf-8"?>
+<!-- app settings -->
+<configuration>
+  <appSettings>
+    <add key="Environment" value="prod" />
+    <add key="ApiKey" value="a&amp;b" />
+  </appSettings>
+  <connectionStrings>
+    <add name="Main" connectionString="Server=db;Password=secret" />
+  </connectionStrings>
+  <system.web>
+    <compilation debug="false">net48</compilation>
+    <customErrors mode="On"/>
+  </system.web>
+  <banner><![CDATA[<b>hi</b>]]> &lt;3 &#x41;</banner>
+  <empty/>
+</configuration>
+"#;
+        let value = parse(content).unwrap();
+        let config = &value["configuration"];
+        assert_eq!(config["appSettings"]["add"][1]["@key"], "ApiKey");
+        assert_eq!(config["appSettings"]["add"][1]["@value"], "a&b");
+        assert_eq!(
+            config["connectionStrings"]["add"]["@connectionString"],
+            "Server=db;Password=secret"
+        );
+        assert_eq!(config["system.web"]["compilation"]["@debug"], "false");
+        assert_eq!(config["system.web"]["compilation"]["#text"], "net48");
+        assert_eq!(config["banner"], "<b>hi</b> <3 A");
+        assert_eq!(config["empty"], "");
+    }
+
+    #[test]
+    fn test_parse_xml_errors() {
+        let err = parse("<a>\n  <b>\n  </c>\n</a>\n").unwrap_err();
+        assert!(err.to_string().contains("line 3"), "{}", err);
+        assert!(parse("<a><b></b>").is_err());
+    }
+}
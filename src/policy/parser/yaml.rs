@@ -0,0 +1,596 @@
+use anyhow::{Result, bail};
+use serde::de::{self, Deserialize, Deserializer, EnumAccess, MapAccess, SeqAccess, VariantAccess};
+use serde_yaml::value::{Tag, TaggedValue};
+use std::collections::HashMap;
+use std::fmt;
+use yaml_rust2::parser::{Event, MarkedEventReceiver, Parser};
+use yaml_rust2::scanner::{Marker, Scanner, TScalarStyle, Token, TokenType};
+
+use super::spans::SourceMap;
+use crate::keypath::{KeyPath, join_index, join_key};
+use crate::models::{DuplicateKey, Origin, Span};
+
+const MERGE_KEY: &str = "<<";
+
+/// A value read like `serde_yaml::Value`, except that a key repeated in a map keeps
+/// its last value, as JSON parsers do, instead of failing the whole file. The
+/// duplicates are reported from the source map. Every format is read into this, since
+/// unlike `serde_json::Value` it keeps NaN and infinite floats.
+/// Map keys must be strings, numbers or booleans.
+pub(super) struct LastKeyWins(pub(super) serde_yaml::Value);
+
+impl<'de> Deserialize<'de> for LastKeyWins {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer
+            .deserialize_any(LastKeyWinsVisitor)
+            .map(LastKeyWins)
+    }
+}
+
+struct LastKeyWinsVisitor;
+
+impl<'de> de::Visitor<'de> for LastKeyWinsVisitor {
+    type Value = serde_yaml::Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("any YAML value")
+    }
+
+    fn visit_bool<E: de::Error>(self, b: bool) -> Result<Self::Value, E> {
+        Ok(serde_yaml::Value::Bool(b))
+    }
+
+    fn visit_i64<E: de::Error>(self, i: i64) -> Result<Self::Value, E> {
+        Ok(serde_yaml::Value::Number(i.into()))
+    }
+
+    fn visit_u64<E: de::Error>(self, u: u64) -> Result<Self::Value, E> {
+        Ok(serde_yaml::Value::Number(u.into()))
+    }
+
+    fn visit_f64<E: de::Error>(self, f: f64) -> Result<Self::Value, E> {
+        Ok(serde_yaml::Value::Number(f.into()))
+    }
+
+    fn visit_str<E: de::Error>(self, s: &str) -> Result<Self::Value, E> {
+        Ok(serde_yaml::Value::String(s.to_string()))
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+        Ok(serde_yaml::Value::Null)
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Self::Value, E> {
+        Ok(serde_yaml::Value::Null)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        LastKeyWins::deserialize(deserializer).map(|v| v.0)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut items: A) -> Result<Self::Value, A::Error> {
+        let mut sequence = Vec::new();
+        while let Some(LastKeyWins(item)) = items.next_element()? {
+            sequence.push(item);
+        }
+        Ok(serde_yaml::Value::Sequence(sequence))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut entries: A) -> Result<Self::Value, A::Error> {
+        let mut mapping = serde_yaml::Mapping::new();
+        while let Some((LastKeyWins(key), LastKeyWins(value))) = entries.next_entry()? {
+            if !matches!(
+                key,
+                serde_yaml::Value::String(_)
+                    | serde_yaml::Value::Number(_)
+                    | serde_yaml::Value::Bool(_)
+            ) {
+                return Err(de::Error::custom(
+                    "map keys must be strings, numbers or booleans",
+                ));
+            }
+            mapping.insert(key, value);
+        }
+        Ok(serde_yaml::Value::Mapping(mapping))
+    }
+
+    /// A tagged value (`!Ref db`).
+    fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<Self::Value, A::Error> {
+        let (tag, contents): (String, _) = data.variant()?;
+        if tag.is_empty() {
+            return Err(de::Error::custom("empty YAML tag is not allowed"));
+        }
+        let LastKeyWins(value) = contents.newtype_variant()?;
+        Ok(serde_yaml::Value::Tagged(Box::new(TaggedValue {
+            tag: Tag::new(tag),
+            value,
+        })))
+    }
+}
+
+/// Resolve `<<` merge keys in place. Keys written in the map win over merged ones,
+/// and among several merged maps (`<<: [*a, *b]`) the earlier one wins. Merged maps
+/// are resolved first, so an anchor that itself merges another passes on both.
+pub(super) fn resolve_merges(value: &mut serde_yaml::Value) -> Result<()> {
+    match value {
+        serde_yaml::Value::Mapping(map) => {
+            for child in map.values_mut() {
+                resolve_merges(child)?;
+            }
+            let sources = match map.remove(MERGE_KEY) {
+                None => Vec::new(),
+                Some(serde_yaml::Value::Mapping(source)) => vec![source],
+                Some(serde_yaml::Value::Sequence(items)) => items
+                    .into_iter()
+                    .map(|item| match item {
+                        serde_yaml::Value::Mapping(source) => Ok(source),
+                        _ => bail!("Merge key `<<` lists a value that is not a map"),
+                    })
+                    .collect::<Result<_>>()?,
+                Some(_) => bail!("Merge key `<<` must be a map or a list of maps"),
+            };
+            for source in sources {
+                for (key, value) in source {
+                    map.entry(key).or_insert(value);
+                }
+            }
+        }
+        serde_yaml::Value::Sequence(items) => {
+            for item in items {
+                resolve_merges(item)?;
+            }
+        }
+        serde_yaml::Value::Tagged(tagged) => resolve_merges(&mut tagged.value)?,
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Source maps of each YAML document, skipping empty documents the same way the parser
+/// does. Mapping entries point at their key and list items at the item. Keys that come
+/// from an anchor point at the alias or `<<` that uses it, with the anchor as origin.
+/// A key written twice in one mapping is recorded as a duplicate where the mapping is
+/// written, not again everywhere its anchor is used.
+pub(super) fn yaml_source_maps(content: &str) -> Vec<SourceMap> {
+    let mut tree = YamlTree::default();
+    if Parser::new_from_str(content).load(&mut tree, true).is_err() {
+        return Vec::new();
+    }
+    let names = anchor_names(content);
+    tree.documents
+        .iter()
+        .map(|root| {
+            let mut walker = Walker {
+                anchors: &tree.anchors,
+                names: &names,
+                map: SourceMap::default(),
+            };
+            walker.node(root, "", root.span, None);
+            walker.map
+        })
+        .collect()
+}
+
+/// Anchor names in the order they appear, which is the order the parser numbers
+/// them in from 1. Its events only carry the number.
+fn anchor_names(content: &str) -> Vec<String> {
+    Scanner::new(content.chars())
+        .filter_map(|Token(_, token)| match token {
+            TokenType::Anchor(name) => Some(name),
+            _ => None,
+        })
+        .collect()
+}
+
+fn span(mark: Marker) -> Span {
+    Span {
+        line: mark.line(),
+        column: mark.col() + 1,
+    }
+}
+
+#[derive(Clone)]
+struct Node {
+    span: Span,
+    kind: NodeKind,
+}
+
+#[derive(Clone)]
+enum NodeKind {
+    Scalar,
+    /// Refers to an anchored node by number.
+    Alias(usize),
+    Mapping(Vec<Entry>),
+    Sequence(Vec<Node>),
+}
+
+#[derive(Clone)]
+struct Entry {
+    key: String,
+    span: Span,
+    value: Node,
+}
+
+/// A mapping or sequence whose end has not been reached yet.
+struct Open {
+    span: Span,
+    anchor: usize,
+    kind: NodeKind,
+    /// For a mapping, the key whose value comes next.
+    key: Option<(String, Span)>,
+}
+
+/// Builds a node tree per document, keeping anchored nodes by number for aliases.
+#[derive(Default)]
+struct YamlTree {
+    stack: Vec<Open>,
+    root: Option<Node>,
+    /// The document is a bare null, which the parser skips.
+    null_root: bool,
+    anchors: HashMap<usize, Node>,
+    documents: Vec<Node>,
+}
+
+impl YamlTree {
+    /// Attach a finished node to its parent. `text` is the key when the node is one.
+    fn push(&mut self, node: Node, anchor: usize, text: &str) {
+        if anchor != 0 {
+            self.anchors.insert(anchor, node.clone());
+        }
+        let Some(open) = self.stack.last_mut() else {
+            self.root = Some(node);
+            return;
+        };
+        match &mut open.kind {
+            NodeKind::Mapping(entries) => match open.key.take() {
+                Some((key, span)) => entries.push(Entry {
+                    key,
+                    span,
+                    value: node,
+                }),
+                None => open.key = Some((text.to_string(), node.span)),
+            },
+            NodeKind::Sequence(items) => items.push(node),
+            NodeKind::Scalar | NodeKind::Alias(_) => {}
+        }
+    }
+
+    fn open(&mut self, kind: NodeKind, anchor: usize, mark: Marker) {
+        self.stack.push(Open {
+            span: span(mark),
+            anchor,
+            kind,
+            key: None,
+        });
+    }
+}
+
+impl MarkedEventReceiver for YamlTree {
+    fn on_event(&mut self, event: Event, mark: Marker) {
+        match event {
+            Event::DocumentStart => {
+                self.stack.clear();
+                self.root = None;
+                self.null_root = false;
+            }
+            Event::DocumentEnd if !self.null_root => {
+                self.documents.extend(self.root.take());
+            }
+            Event::Scalar(value, style, anchor, _) => {
+                if self.stack.is_empty() {
+                    self.null_root = style == TScalarStyle::Plain
+                        && matches!(value.as_str(), "" | "~" | "null" | "Null" | "NULL");
+                }
+                let node = Node {
+                    span: span(mark),
+                    kind: NodeKind::Scalar,
+                };
+                self.push(node, anchor, &value);
+            }
+            Event::Alias(id) => {
+                let node = Node {
+                    span: span(mark),
+                    kind: NodeKind::Alias(id),
+                };
+                self.push(node, 0, "");
+            }
+            Event::MappingStart(anchor, _) => {
+                self.open(NodeKind::Mapping(Vec::new()), anchor, mark)
+            }
+            Event::SequenceStart(anchor, _) => {
+                self.open(NodeKind::Sequence(Vec::new()), anchor, mark)
+            }
+            Event::MappingEnd | Event::SequenceEnd => {
+                if let Some(open) = self.stack.pop() {
+                    let node = Node {
+                        span: open.span,
+                        kind: open.kind,
+                    };
+                    self.push(node, open.anchor, "");
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Keys below an alias or a merge key: the anchor their value is written in, and
+/// where in the document that anchor is used.
+#[derive(Clone, Copy)]
+struct Inherited {
+    anchor: usize,
+    used_at: Span,
+}
+
+/// A mapping entry after merge keys are resolved.
+struct Merged<'a> {
+    key: &'a str,
+    span: Span,
+    value: &'a Node,
+    /// Anchor the entry was merged from, and the `<<` key that merged it.
+    from: Option<(usize, Span)>,
+}
+
+struct Walker<'a> {
+    anchors: &'a HashMap<usize, Node>,
+    names: &'a [String],
+    map: SourceMap,
+}
+
+impl<'a> Walker<'a> {
+    /// Record the keys below `node`, which is at `path` and located at `span`.
+    fn node(&mut self, node: &'a Node, path: &str, span: Span, inherited: Option<Inherited>) {
+        match &node.kind {
+            NodeKind::Scalar => {}
+            NodeKind::Alias(id) => {
+                let Some(target) = self.anchors.get(id) else {
+                    return;
+                };
+                if !path.is_empty()
+                    && let Some(origin) = self.origin(*id, target.span)
+                {
+                    self.map.insert_origin(path.to_string(), origin);
+                }
+                let inherited = Inherited {
+                    anchor: *id,
+                    used_at: span,
+                };
+                self.node(target, path, span, Some(inherited));
+            }
+            NodeKind::Mapping(own) => {
+                if inherited.is_none() {
+                    self.duplicates(own, path);
+                }
+                for entry in self.entries(node) {
+                    let (anchor, used_at) = match (inherited, entry.from) {
+                        (Some(outer), from) => (
+                            Some(from.map_or(outer.anchor, |(anchor, _)| anchor)),
+                            outer.used_at,
+                        ),
+                        (None, Some((anchor, merged_at))) => (Some(anchor), merged_at),
+                        (None, None) => (None, entry.span),
+                    };
+                    let inherited = anchor.map(|anchor| Inherited { anchor, used_at });
+                    let child = join_key(path, entry.key);
+                    self.record(&child, entry.span, inherited);
+                    self.node(entry.value, &child, used_at, inherited);
+                }
+            }
+            NodeKind::Sequence(items) => {
+                for (index, item) in items.iter().enumerate() {
+                    let child = join_index(path, index);
+                    self.record(&child, item.span, inherited);
+                    let used_at = inherited.map_or(item.span, |outer| outer.used_at);
+                    self.node(item, &child, used_at, inherited);
+                }
+            }
+        }
+    }
+
+    /// Record a key written at `span`, or where its anchor is used when it is inherited.
+    fn record(&mut self, key: &str, span: Span, inherited: Option<Inherited>) {
+        match inherited {
+            Some(inherited) => {
+                self.map.insert(key.to_string(), inherited.used_at);
+                if let Some(origin) = self.origin(inherited.anchor, span) {
+                    self.map.insert_origin(key.to_string(), origin);
+                }
+            }
+            None => self.map.insert(key.to_string(), span),
+        }
+    }
+
+    /// Record the entries of a mapping whose key was already written in it.
+    fn duplicates(&mut self, own: &[Entry], path: &str) {
+        let mut seen: HashMap<&str, Span> = HashMap::new();
+        for entry in own {
+            match seen.get(entry.key.as_str()) {
+                Some(&first) => self.map.insert_duplicate(DuplicateKey {
+                    key: KeyPath::from_flat(&join_key(path, &entry.key)),
+                    span: entry.span,
+                    first,
+                }),
+                None => {
+                    seen.insert(&entry.key, entry.span);
+                }
+            }
+        }
+    }
+
+    fn origin(&self, anchor: usize, span: Span) -> Option<Origin> {
+        let name = self.names.get(anchor.checked_sub(1)?)?;
+        Some(Origin {
+            anchor: name.clone(),
+            span,
+        })
+    }
+
+    /// Entries of a mapping with `<<` merge keys resolved the way `resolve_merges` does.
+    fn entries(&self, node: &'a Node) -> Vec<Merged<'a>> {
+        let NodeKind::Mapping(own) = &node.kind else {
+            return Vec::new();
+        };
+        let mut entries: Vec<Merged<'a>> = own
+            .iter()
+            .filter(|entry| entry.key != MERGE_KEY)
+            .map(|entry| Merged {
+                key: &entry.key,
+                span: entry.span,
+                value: &entry.value,
+                from: None,
+            })
+            .collect();
+        for merge in own.iter().filter(|entry| entry.key == MERGE_KEY) {
+            let sources: Vec<&'a Node> = match &merge.value.kind {
+                NodeKind::Sequence(items) => items.iter().collect(),
+                _ => vec![&merge.value],
+            };
+            for source in sources {
+                let (source, anchor) = match source.kind {
+                    NodeKind::Alias(id) => match self.anchors.get(&id) {
+                        Some(target) => (target, Some(id)),
+                        None => continue,
+                    },
+                    _ => (source, None),
+                };
+                for inherited in self.entries(source) {
+                    if entries.iter().any(|entry| entry.key == inherited.key) {
+                        continue;
+                    }
+                    let anchor = inherited.from.map(|(anchor, _)| anchor).or(anchor);
+                    entries.push(Merged {
+                        from: anchor.map(|anchor| (anchor, merge.span)),
+                        ..inherited
+                    });
+                }
+            }
+        }
+        entries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keypath::KeyPath;
+
+    fn key(path: &str) -> KeyPath {
+        KeyPath::parse(path).unwrap()
+    }
+
+    fn at(line: usize, column: usize) -> Option<Span> {
+        Some(Span { line, column })
+    }
+
+    #[test]
+    fn test_yaml_spans() {
+        let content = "---\nserver:\n  host: localhost\n  ports:\n    - 80\n    - name: tls\n---\n---\nother: 1\n";
+        let documents = yaml_source_maps(content);
+        assert_eq!(documents.len(), 2);
+        let spans = &documents[0];
+        assert_eq!(spans.span(&key("server")), at(2, 1));
+        assert_eq!(spans.span(&key("server.host")), at(3, 3));
+        assert_eq!(spans.span(&key("server.ports[0]")), at(5, 7));
+        assert_eq!(spans.span(&key("server.ports[1].name")), at(6, 7));
+        assert_eq!(documents[1].span(&key("other")), at(9, 1));
+    }
+
+    #[test]
+    fn test_resolve_merges() {
+        let content = r#"
+base: &base
+  image: nginx
+  restart: always
+defaults: &defaults
+  <<: *base
+  replicas: 2
+other: &other
+  replicas: 5
+  debug: true
+api:
+  <<: [*defaults, *other]
+  restart: never
+"#;
+        let mut value: serde_yaml::Value = serde_yaml::from_str(content).unwrap();
+        resolve_merges(&mut value).unwrap();
+        let api = &value["api"];
+        assert_eq!(api["image"], "nginx");
+        assert_eq!(api["restart"], "never");
+        assert_eq!(api["replicas"], 2);
+        assert_eq!(api["debug"], true);
+        assert!(api.get(MERGE_KEY).is_none());
+        assert!(value["defaults"].get(MERGE_KEY).is_none());
+
+        let mut scalar: serde_yaml::Value = serde_yaml::from_str("a:\n  <<: 5\n").unwrap();
+        assert!(resolve_merges(&mut scalar).is_err());
+    }
+
+    #[test]
+    fn test_inherited_keys_point_at_anchor() {
+        let content = r#"base: &base
+  image: nginx
+defaults: &defaults
+  <<: *base
+  replicas: 2
+api:
+  <<: *defaults
+  replicas: 3
+worker:
+  settings: *defaults
+  tag: &tag v1
+  version: *tag
+"#;
+        let maps = yaml_source_maps(content);
+        let map = &maps[0];
+
+        // Written in place: no origin
+        assert_eq!(map.span(&key("api.replicas")), at(8, 3));
+        assert_eq!(map.origin(&key("api.replicas")), None);
+
+        // Merged through two anchors: blamed on the `<<`, written in `base`
+        assert_eq!(map.span(&key("api.image")), at(7, 3));
+        let origin = map.origin(&key("api.image")).unwrap();
+        assert_eq!(origin.anchor, "base");
+        assert_eq!(origin.span.line, 2);
+
+        let origin = map.origin(&key("worker.settings.replicas")).unwrap();
+        assert_eq!(origin.anchor, "defaults");
+        assert_eq!(origin.span.line, 5);
+        assert_eq!(map.span(&key("worker.settings.replicas")), at(10, 3));
+        assert_eq!(
+            map.origin(&key("worker.settings.image")).unwrap().anchor,
+            "base"
+        );
+
+        assert_eq!(map.span(&key("worker.version")), at(12, 3));
+        assert_eq!(map.origin(&key("worker.version")).unwrap().anchor, "tag");
+        assert_eq!(
+            map.origin(&key("worker.version")).unwrap().to_string(),
+            "inherited from anchor `tag` at line 11"
+        );
+    }
+
+    #[test]
+    fn test_duplicate_keys() {
+        let content = r#"base: &base
+  image: nginx
+  image: redis
+server:
+  port: 80
+  tls: true
+  port: 8080
+api:
+  <<: *base
+  image: app
+"#;
+        let maps = yaml_source_maps(content);
+        let duplicates = maps[0].duplicates();
+        // Overriding a merged key is not a duplicate, and the anchor is reported once
+        assert_eq!(duplicates.len(), 2);
+        assert_eq!(duplicates[0].key, "base.image");
+        assert_eq!((duplicates[0].first.line, duplicates[0].span.line), (2, 3));
+        assert_eq!(duplicates[1].key, "server.port");
+        assert_eq!((duplicates[1].first.line, duplicates[1].span.line), (5, 7));
+        assert_eq!(maps[0].span(&key("server.port")), at(7, 3));
+    }
+}
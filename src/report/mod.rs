@@ -2,10 +2,12 @@ mod models;
 mod output;
 
 use anyhow::Result;
+use rayon::prelude::*;
+use std::collections::BTreeMap;
 use std::path::Path;
-use walkdir::WalkDir;
 
-use crate::utils::{hash_file, is_config};
+use crate::cache::Cache;
+use crate::walk::{self, WalkOptions};
 use models::{AuditReport, InventoryEntry, OverviewSection, RiskLevel};
 
 /// Generate a unified audit report. Returns true if risk level is Fail.
@@ -14,8 +16,11 @@ pub fn generate_report(
     policy_path: Option<&str>,
     format: &str,
     output_file: Option<&str>,
+    walk: &WalkOptions,
+    cache: &Cache,
 ) -> Result<bool> {
-    let report = collect_report(path, policy_path)?;
+    let report = collect_report(path, policy_path, walk, cache)?;
+    cache.save()?;
     let is_fail = report.risk_level == RiskLevel::Fail;
 
     match format {
@@ -27,54 +32,49 @@ pub fn generate_report(
     Ok(is_fail)
 }
 
-fn collect_report(path: &str, policy_path: Option<&str>) -> Result<AuditReport> {
+fn collect_report(
+    path: &str,
+    policy_path: Option<&str>,
+    walk: &WalkOptions,
+    cache: &Cache,
+) -> Result<AuditReport> {
     // 1. Collect config inventory
-    let mut inventory = Vec::new();
-    let mut yaml_count = 0;
-    let mut json_count = 0;
-    let mut toml_count = 0;
-
-    for entry in WalkDir::new(path).into_iter().filter_map(|e| e.ok()) {
-        let file_path = entry.path();
-        if !file_path.is_file() || !is_config(file_path) {
-            continue;
-        }
-
-        let ext = file_path
-            .extension()
-            .and_then(|s| s.to_str())
-            .unwrap_or("")
-            .to_string();
-
-        match ext.as_str() {
-            "yaml" | "yml" => yaml_count += 1,
-            "json" => json_count += 1,
-            "toml" => toml_count += 1,
-            _ => {}
-        }
+    let inventory: Vec<InventoryEntry> = walk::config_files(path, walk)?
+        .par_iter()
+        .map(|file_path| {
+            let format = file_path.format().map_or("unknown", |f| f.name());
+            let hash = cache
+                .hash(file_path)
+                .unwrap_or_else(|_| "error".to_string());
+            InventoryEntry {
+                path: file_path.display(),
+                hash,
+                canonical_hash: cache.canonical_hash(file_path).ok().flatten(),
+                format: format.to_string(),
+            }
+        })
+        .collect();
 
-        let hash = hash_file(file_path).unwrap_or_else(|_| "error".to_string());
-        inventory.push(InventoryEntry {
-            path: file_path.display().to_string(),
-            hash,
-            format: ext,
-        });
+    let mut formats = BTreeMap::new();
+    for entry in &inventory {
+        *formats.entry(entry.format.clone()).or_insert(0) += 1;
     }
 
-    inventory.sort_by(|a, b| a.path.cmp(&b.path));
     let total_files = inventory.len();
+    let count = |name: &str| formats.get(name).copied().unwrap_or(0);
 
     let overview = OverviewSection {
         generated_at: chrono::Utc::now().to_rfc3339(),
         path: path.to_string(),
         total_files,
-        yaml_count,
-        json_count,
-        toml_count,
+        yaml_count: count("yaml"),
+        json_count: count("json"),
+        toml_count: count("toml"),
+        formats,
     };
 
     // 2. Secret scan
-    let secrets = match crate::secrets::get_report(path) {
+    let secrets = match crate::secrets::get_report(path, walk, cache) {
         Ok(r) => Some(r),
         Err(e) => {
             eprintln!("Warning: Secret scan failed: {}", e);
@@ -82,26 +82,27 @@ fn collect_report(path: &str, policy_path: Option<&str>) -> Result<AuditReport>
         }
     };
 
-    // 3. Policy evaluation (if policy provided)
-    let policy = if let Some(pp) = policy_path {
-        match crate::policy::loader::load_policy(Path::new(pp)) {
-            Ok(policy_file) => {
-                match crate::policy::evaluator::evaluate_policy(path, &policy_file) {
-                    Ok(r) => Some(r),
-                    Err(e) => {
-                        eprintln!("Warning: Policy evaluation failed: {}", e);
-                        None
-                    }
-                }
-            }
+    // 3. Policy evaluation (only the built-in duplicate key check without a policy)
+    let policy_file = match policy_path {
+        Some(pp) => match crate::policy::loader::load_policy(Path::new(pp)) {
+            Ok(policy_file) => Some(policy_file),
             Err(e) => {
                 eprintln!("Warning: Failed to load policy: {}", e);
                 None
             }
-        }
-    } else {
-        None
+        },
+        None => Some(crate::policy::loader::builtin_policy()),
     };
+    let policy =
+        policy_file.and_then(|policy_file| {
+            match crate::policy::evaluator::evaluate_policy(path, &policy_file, walk, cache) {
+                Ok(r) => Some(r),
+                Err(e) => {
+                    eprintln!("Warning: Policy evaluation failed: {}", e);
+                    None
+                }
+            }
+        });
 
     // 4. Git change history
     let git_changes = match crate::git::collect_log(None, 5) {
@@ -125,15 +126,15 @@ fn collect_report(path: &str, policy_path: Option<&str>) -> Result<AuditReport>
     };
 
     let mut summary_parts = Vec::new();
-    if let Some(s) = &secrets {
-        if s.total_findings > 0 {
-            summary_parts.push(format!("{} secrets found", s.total_findings));
-        }
+    if let Some(s) = &secrets
+        && s.total_findings > 0
+    {
+        summary_parts.push(format!("{} secrets found", s.total_findings));
     }
-    if let Some(p) = &policy {
-        if p.total_violations > 0 {
-            summary_parts.push(format!("{} policy violations", p.total_violations));
-        }
+    if let Some(p) = &policy
+        && p.total_violations > 0
+    {
+        summary_parts.push(format!("{} policy violations", p.total_violations));
     }
 
     let risk_summary = if summary_parts.is_empty() {
@@ -152,3 +153,37 @@ fn collect_report(path: &str, policy_path: Option<&str>) -> Result<AuditReport>
         risk_summary,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_duplicate_keys_reported_without_policy() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("app.yaml"),
+            "debug: false\nport: 80\ndebug: true\n",
+        )
+        .unwrap();
+        let root = dir.path().to_str().unwrap();
+
+        let report = collect_report(
+            root,
+            None,
+            &WalkOptions::default(),
+            &Cache::open(None).unwrap(),
+        )
+        .unwrap();
+        let policy = report.policy.unwrap();
+        assert_eq!(policy.policy_name, crate::policy::loader::BUILTIN_POLICY);
+        assert_eq!(policy.medium_count, 1);
+        let violation = &policy.files[0].violations[0];
+        assert_eq!(
+            violation.rule_id,
+            crate::policy::evaluator::DUPLICATE_KEY_RULE
+        );
+        assert_eq!(violation.span.map(|s| s.line), Some(3));
+        assert_eq!(report.risk_level, RiskLevel::Warn);
+    }
+}
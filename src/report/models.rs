@@ -1,4 +1,5 @@
 use serde::Serialize;
+use std::collections::BTreeMap;
 
 use crate::git::models::GitLogReport;
 use crate::models::SecretReport;
@@ -29,9 +30,12 @@ pub struct OverviewSection {
     pub generated_at: String,
     pub path: String,
     pub total_files: usize,
+    /// Counts from before `formats` was added, kept for existing consumers.
     pub yaml_count: usize,
     pub json_count: usize,
     pub toml_count: usize,
+    /// Number of files per format name (`yaml`, `json`, `toml`, `dotenv`, `xml`, ...).
+    pub formats: BTreeMap<String, usize>,
 }
 
 /// A single file in the config inventory.
@@ -39,6 +43,10 @@ pub struct OverviewSection {
 pub struct InventoryEntry {
     pub path: String,
     pub hash: String,
+    /// Hash of the parsed, key-sorted content; absent if the file did not parse.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub canonical_hash: Option<String>,
+    /// Format name, e.g. `yaml` for both `.yml` and `.yaml` files.
     pub format: String,
 }
 
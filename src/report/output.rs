@@ -1,4 +1,5 @@
 use anyhow::Result;
+use std::collections::BTreeMap;
 use std::io::Write;
 use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
 
@@ -15,19 +16,13 @@ pub fn output_terminal(report: &AuditReport) -> Result<()> {
     writeln!(&mut stdout, "ConfigTrace Audit Report")?;
     writeln!(&mut stdout, "========================")?;
     stdout.reset()?;
-    writeln!(
-        &mut stdout,
-        "Generated: {}",
-        report.overview.generated_at
-    )?;
+    writeln!(&mut stdout, "Generated: {}", report.overview.generated_at)?;
     writeln!(&mut stdout, "Path: {}", report.overview.path)?;
     writeln!(
         &mut stdout,
-        "Files: {} ({} yaml, {} json, {} toml)",
+        "Files: {} ({})",
         report.overview.total_files,
-        report.overview.yaml_count,
-        report.overview.json_count,
-        report.overview.toml_count
+        format_counts(&report.overview.formats)
     )?;
 
     // Config Inventory
@@ -66,6 +61,11 @@ pub fn output_terminal(report: &AuditReport) -> Result<()> {
             writeln!(&mut stdout)?;
             for file in &secrets.files {
                 writeln!(&mut stdout, "  {}:", file.path)?;
+                if let Some(mode) = &file.exposed_mode {
+                    stdout.set_color(ColorSpec::new().set_fg(Some(Color::Yellow)))?;
+                    writeln!(&mut stdout, "    Readable by group/others (mode {})", mode)?;
+                    stdout.reset()?;
+                }
                 for finding in &file.findings {
                     let color = match finding.severity {
                         crate::models::Severity::Critical => Color::Red,
@@ -118,7 +118,11 @@ pub fn output_terminal(report: &AuditReport) -> Result<()> {
                     stdout.set_color(ColorSpec::new().set_fg(Some(color)))?;
                     write!(&mut stdout, "[{}]", v.severity)?;
                     stdout.reset()?;
-                    writeln!(&mut stdout, " {}: {}", v.rule_id, v.message)?;
+                    write!(&mut stdout, " {}: {}", v.rule_id, v.message)?;
+                    if let Some(location) = v.location() {
+                        write!(&mut stdout, " ({})", location)?;
+                    }
+                    writeln!(&mut stdout)?;
                 }
             }
         }
@@ -137,7 +141,13 @@ pub fn output_terminal(report: &AuditReport) -> Result<()> {
             stdout.reset()?;
 
             let first_line = commit.message.lines().next().unwrap_or("");
-            writeln!(&mut stdout, " - {} ({}, {})", first_line, commit.author, &commit.date[..10.min(commit.date.len())])?;
+            writeln!(
+                &mut stdout,
+                " - {} ({}, {})",
+                first_line,
+                commit.author,
+                &commit.date[..10.min(commit.date.len())]
+            )?;
 
             for file in &commit.files {
                 let total = file.keys_added + file.keys_removed + file.keys_changed;
@@ -196,6 +206,15 @@ fn write_severity_counts(
     Ok(())
 }
 
+/// "2 yaml, 1 dotenv" style breakdown of the inventory by format.
+fn format_counts(formats: &BTreeMap<String, usize>) -> String {
+    formats
+        .iter()
+        .map(|(format, count)| format!("{} {}", count, format))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
 // ===== JSON Output =====
 
 pub fn output_json(report: &AuditReport, output_file: Option<&str>) -> Result<()> {
@@ -217,13 +236,11 @@ pub fn output_markdown(report: &AuditReport, output_file: Option<&str>) -> Resul
     // Header
     md.push_str("# ConfigTrace Audit Report\n\n");
     md.push_str(&format!(
-        "**Generated:** {} | **Path:** `{}` | **Files:** {} ({} yaml, {} json, {} toml)\n\n",
+        "**Generated:** {} | **Path:** `{}` | **Files:** {} ({})\n\n",
         report.overview.generated_at,
         report.overview.path,
         report.overview.total_files,
-        report.overview.yaml_count,
-        report.overview.json_count,
-        report.overview.toml_count,
+        format_counts(&report.overview.formats),
     ));
 
     // Config Inventory
@@ -261,13 +278,13 @@ pub fn output_markdown(report: &AuditReport, output_file: Option<&str>) -> Resul
 
             for file in &secrets.files {
                 md.push_str(&format!("### `{}`\n\n", file.path));
+                if let Some(mode) = &file.exposed_mode {
+                    md.push_str(&format!("> Readable by group/others (mode `{}`)\n\n", mode));
+                }
                 for finding in &file.findings {
                     md.push_str(&format!(
                         "- **[{:?}]** Line {}: {} - `{}`\n",
-                        finding.severity,
-                        finding.line,
-                        finding.matched_pattern,
-                        finding.snippet
+                        finding.severity, finding.line, finding.matched_pattern, finding.snippet
                     ));
                 }
                 md.push('\n');
@@ -297,9 +314,13 @@ pub fn output_markdown(report: &AuditReport, output_file: Option<&str>) -> Resul
                 md.push_str(&format!("### `{}`\n\n", file.path));
                 for v in &file.violations {
                     md.push_str(&format!(
-                        "- **[{}]** `{}`: {}\n",
+                        "- **[{}]** `{}`: {}",
                         v.severity, v.rule_id, v.message
                     ));
+                    if let Some(location) = v.location() {
+                        md.push_str(&format!(" ({})", location));
+                    }
+                    md.push('\n');
                 }
                 md.push('\n');
             }
@@ -336,10 +357,7 @@ pub fn output_markdown(report: &AuditReport, output_file: Option<&str>) -> Resul
         RiskLevel::Warn => "WARN",
         RiskLevel::Fail => "FAIL",
     };
-    md.push_str(&format!(
-        "**{}** -- {}\n",
-        icon, report.risk_summary
-    ));
+    md.push_str(&format!("**{}** -- {}\n", icon, report.risk_summary));
 
     // Output
     if let Some(path) = output_file {
@@ -1,29 +1,278 @@
 use anyhow::Result;
 use chrono::Utc;
+use git2::{Commit, Repository};
+use rayon::prelude::*;
 use std::fs;
-use walkdir::WalkDir;
+use std::path::Path;
 
+use crate::cache::Cache;
+use crate::git::repo;
 use crate::models::{Entry, Snapshot};
-use crate::utils::{hash_file, is_config};
+use crate::policy::parser::parse_config_content;
+use crate::signing;
+use crate::utils::{hash_bytes, hash_flat_map};
+use crate::walk::{self, WalkOptions};
 
-/// Scan a directory recursively and create a snapshot of config files
-pub fn scan(path: &str, out: &str) -> Result<()> {
-    let mut entries = Vec::new();
-    for e in WalkDir::new(path).into_iter().filter_map(|e| e.ok()) {
-        let p = e.path();
-        if p.is_file() && is_config(p) {
-            let h = hash_file(p)?;
-            entries.push(Entry {
-                path: p.display().to_string(),
-                hash: h,
-            });
+/// Scan a directory recursively and write a snapshot of config files.
+/// Each entry records file metadata (mode, owner, size, mtime, symlink target).
+/// With `with_keys`, each entry also embeds its flattened key map.
+/// With `sign_key`, the snapshot is signed so later edits can be detected.
+/// With `git_ref`, files are read from that commit instead of the working directory.
+pub fn scan(
+    path: &str,
+    out: &str,
+    with_keys: bool,
+    sign_key: Option<&str>,
+    git_ref: Option<&str>,
+    walk: &WalkOptions,
+    cache: &Cache,
+) -> Result<()> {
+    let mut snapshot = match git_ref {
+        Some(reference) => collect_snapshot_at_ref(path, reference, with_keys, walk)?,
+        None => {
+            let snapshot = collect_snapshot(path, with_keys, walk, cache)?;
+            cache.save()?;
+            snapshot
         }
-    }
-    let snapshot = Snapshot {
-        created_at: Utc::now().to_rfc3339(),
-        entries,
     };
+    if let Some(key_path) = sign_key {
+        let key = signing::load_signing_key(key_path)?;
+        signing::sign_snapshot(&mut snapshot, &key)?;
+    }
     fs::write(out, serde_json::to_vec_pretty(&snapshot)?)?;
     println!("Wrote snapshot to {out}");
     Ok(())
 }
+
+/// Walk a directory and build a snapshot without writing it.
+/// Used by `verify` to compare the live tree against a baseline.
+pub fn collect_snapshot(
+    path: &str,
+    with_keys: bool,
+    walk: &WalkOptions,
+    cache: &Cache,
+) -> Result<Snapshot> {
+    let entries = walk::config_files(path, walk)?
+        .par_iter()
+        .map(|p| {
+            let keys = if with_keys {
+                match cache.parse(p) {
+                    Ok(map) => Some(map.into_iter().collect()),
+                    Err(e) => {
+                        eprintln!("Warning: Could not parse {}: {}", p.display(), e);
+                        None
+                    }
+                }
+            } else {
+                None
+            };
+            Ok(Entry {
+                path: p.display(),
+                hash: cache.hash(p)?,
+                canonical_hash: cache.canonical_hash(p)?,
+                keys,
+                metadata: p.metadata(),
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(Snapshot {
+        created_at: Utc::now().to_rfc3339(),
+        entries,
+        git_commit: None,
+        previous_hash: None,
+        signature: None,
+    })
+}
+
+/// Build a snapshot from a commit's tree without checking it out.
+///
+/// `path` is relative to the repository root. Entries are named the way a
+/// working-directory scan of `path` names them, so a release snapshot can be
+/// diffed against a live host. Ignore files are not read from the tree, only
+/// `--include`/`--exclude` apply. File metadata is not recorded, since git
+/// only tracks the executable bit.
+pub fn collect_snapshot_at_ref(
+    path: &str,
+    reference: &str,
+    with_keys: bool,
+    walk: &WalkOptions,
+) -> Result<Snapshot> {
+    let repository = repo::open_repo()?;
+    let commit = repo::resolve_ref(&repository, reference)?;
+    snapshot_at_commit(&repository, &commit, path, with_keys, walk)
+}
+
+fn snapshot_at_commit(
+    repository: &Repository,
+    commit: &Commit,
+    path: &str,
+    with_keys: bool,
+    walk: &WalkOptions,
+) -> Result<Snapshot> {
+    let tree = commit.tree()?;
+    let prefix = tree_prefix(path);
+    let overrides = walk::overrides("", walk)?;
+
+    let mut entries = Vec::new();
+    for file_path in repo::list_config_files_in_tree(repository, &tree, prefix)? {
+        let relative = match prefix {
+            Some(p) => file_path
+                .strip_prefix(p)
+                .unwrap_or(&file_path)
+                .trim_start_matches('/'),
+            None => file_path.as_str(),
+        };
+        if !relative.is_empty() && !walk::is_selected(&overrides, relative) {
+            continue;
+        }
+        let Some(bytes) = repo::get_file_bytes_at_commit(repository, commit, &file_path)? else {
+            continue;
+        };
+
+        let parsed = std::str::from_utf8(&bytes)
+            .map_err(anyhow::Error::from)
+            .and_then(|content| parse_config_content(content, Path::new(&file_path)));
+        let canonical_hash = parsed.as_ref().ok().map(hash_flat_map);
+        let keys = if with_keys {
+            match parsed {
+                Ok(map) => Some(map.into_iter().collect()),
+                Err(e) => {
+                    eprintln!("Warning: Could not parse {}: {}", file_path, e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        let entry_path = if relative.is_empty() {
+            path.to_string()
+        } else {
+            Path::new(path).join(relative).display().to_string()
+        };
+        entries.push(Entry {
+            path: entry_path,
+            hash: hash_bytes(&bytes),
+            canonical_hash,
+            keys,
+            metadata: None,
+        });
+    }
+
+    Ok(Snapshot {
+        created_at: Utc::now().to_rfc3339(),
+        entries,
+        git_commit: Some(commit.id().to_string()),
+        previous_hash: None,
+        signature: None,
+    })
+}
+
+/// The tree path to list for a scan path, or None for the repository root.
+fn tree_prefix(path: &str) -> Option<&str> {
+    let trimmed = path.trim_start_matches("./").trim_end_matches('/');
+    match trimmed {
+        "" | "." => None,
+        p => Some(p),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::FlatValue;
+
+    fn commit_files(repo: &Repository, files: &[(&str, &str)]) -> git2::Oid {
+        let workdir = repo.workdir().unwrap();
+        let mut index = repo.index().unwrap();
+        for (name, content) in files {
+            let full = workdir.join(name);
+            fs::create_dir_all(full.parent().unwrap()).unwrap();
+            fs::write(&full, content).unwrap();
+            index.add_path(Path::new(name)).unwrap();
+        }
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let sig = git2::Signature::now("test", "test@example.com").unwrap();
+        repo.commit(Some("HEAD"), &sig, &sig, "init", &tree, &[])
+            .unwrap()
+    }
+
+    #[test]
+    fn test_snapshot_with_keys_drives_key_level_diff() {
+        use crate::diff::compare_snapshots;
+        use crate::git::models::KeyChangeKind;
+
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("app.yaml");
+        let root = dir.path().to_str().unwrap();
+        let cache = Cache::open(None).unwrap();
+        let walk = WalkOptions::default();
+
+        fs::write(&file, "database:\n  host: localhost\n  port: 5432\n").unwrap();
+        let plain = collect_snapshot(root, false, &walk, &cache).unwrap();
+        assert_eq!(plain.entries[0].keys, None);
+        let old = collect_snapshot(root, true, &walk, &cache).unwrap();
+        let keys = old.entries[0].keys.as_ref().unwrap();
+        assert_eq!(
+            keys.get("database.host"),
+            Some(&FlatValue::from("localhost"))
+        );
+        assert_eq!(keys.get("database.port"), Some(&FlatValue::Int(5432)));
+
+        fs::write(&file, "database:\n  host: db.internal\n  port: 5432\n").unwrap();
+        let new = collect_snapshot(root, true, &walk, &cache).unwrap();
+        let report = compare_snapshots(&old, &new, "old.json", "new.json");
+        let changes = &report.changed[0].key_changes;
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].key, "database.host");
+        assert_eq!(changes[0].kind, KeyChangeKind::Changed);
+        assert_eq!(changes[0].new_value, Some(FlatValue::from("db.internal")));
+
+        // Without keys on one side, the diff stays at the file level
+        let report = compare_snapshots(&plain, &new, "old.json", "new.json");
+        assert!(report.changed[0].key_changes.is_empty());
+    }
+
+    #[test]
+    fn test_snapshot_at_commit_matches_working_tree_naming() {
+        let dir = tempfile::tempdir().unwrap();
+        let repository = Repository::init(dir.path()).unwrap();
+        let oid = commit_files(
+            &repository,
+            &[
+                ("infra/db.yaml", "host: db\n"),
+                ("infra/fixtures/test.yaml", "a: 1\n"),
+                ("app.json", "{}"),
+            ],
+        );
+        let commit = repository.find_commit(oid).unwrap();
+        let walk = WalkOptions {
+            exclude: vec!["fixtures/".to_string()],
+            ..Default::default()
+        };
+
+        let snapshot = snapshot_at_commit(&repository, &commit, "./infra/", true, &walk).unwrap();
+        assert_eq!(snapshot.git_commit, Some(oid.to_string()));
+        assert_eq!(snapshot.entries.len(), 1);
+        let entry = &snapshot.entries[0];
+        assert_eq!(entry.path, "./infra/db.yaml");
+        assert_eq!(entry.hash, hash_bytes(b"host: db\n"));
+        assert_eq!(
+            entry.keys.as_ref().unwrap().get("host"),
+            Some(&FlatValue::from("db"))
+        );
+
+        let snapshot =
+            snapshot_at_commit(&repository, &commit, ".", false, &WalkOptions::default()).unwrap();
+        let paths: Vec<_> = snapshot.entries.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            vec![
+                "./app.json",
+                "./infra/db.yaml",
+                "./infra/fixtures/test.yaml"
+            ]
+        );
+    }
+}
@@ -1,10 +1,13 @@
 use anyhow::Result;
 use chrono::Utc;
-use std::fs;
-use walkdir::WalkDir;
+use rayon::prelude::*;
+use std::path::Path;
 
+use crate::cache::Cache;
 use crate::models::{FileSecrets, SecretFinding, SecretReport};
-use crate::utils::is_config;
+use crate::source::ConfigFile;
+use crate::utils::{file_metadata, format_mode};
+use crate::walk::{self, WalkOptions};
 
 use super::patterns::SECRET_PATTERNS;
 
@@ -34,9 +37,20 @@ fn redact_secret(text: &str, start: usize, end: usize) -> String {
     format!("{}...", &secret[..4.min(secret.len())])
 }
 
+/// The file's mode if its group or other users can read it.
+/// A secret in such a file is readable by more than its owner.
+fn exposed_mode(path: &Path) -> Option<String> {
+    let mode = file_metadata(path).ok()?.mode?;
+    (mode & 0o044 != 0).then(|| format_mode(mode))
+}
+
 /// Detect secrets in a single file
-pub fn detect_secrets_in_file(path: &std::path::Path) -> Result<Vec<SecretFinding>> {
-    let content = fs::read_to_string(path)?;
+pub fn detect_secrets_in_file(file: &ConfigFile) -> Result<Vec<SecretFinding>> {
+    Ok(detect_secrets_in_content(&file.read_to_string()?))
+}
+
+/// Detect secrets line by line in a file's content
+pub fn detect_secrets_in_content(content: &str) -> Vec<SecretFinding> {
     let mut findings = Vec::new();
 
     for (line_num, line) in content.lines().enumerate() {
@@ -67,42 +81,45 @@ pub fn detect_secrets_in_file(path: &std::path::Path) -> Result<Vec<SecretFindin
         }
     }
 
-    Ok(findings)
+    findings
 }
 
 /// Scan a directory recursively for secrets in config files
-pub fn scan_directory(path: &str) -> Result<SecretReport> {
-    let mut files_with_secrets = Vec::new();
-    let mut total_files = 0;
+pub fn scan_directory(path: &str, walk: &WalkOptions, cache: &Cache) -> Result<SecretReport> {
+    let files = walk::config_files(path, walk)?;
+    let scanned: Vec<Option<FileSecrets>> = files
+        .par_iter()
+        .map(|p| match cache.secrets(p, || detect_secrets_in_file(p)) {
+            Ok(findings) if !findings.is_empty() => Some(FileSecrets {
+                path: p.display(),
+                findings,
+                exposed_mode: p.disk_path().and_then(exposed_mode),
+            }),
+            Ok(_) => None, // No findings, skip
+            Err(e) => {
+                eprintln!("Warning: Could not scan {}: {}", p.display(), e);
+                None
+            }
+        })
+        .collect();
+
+    let files_with_secrets: Vec<FileSecrets> = scanned.into_iter().flatten().collect();
+    let total_files = files.len();
     let mut critical_count = 0;
     let mut high_count = 0;
-
-    for entry in WalkDir::new(path).into_iter().filter_map(|e| e.ok()) {
-        let p = entry.path();
-        if p.is_file() && is_config(p) {
-            total_files += 1;
-
-            match detect_secrets_in_file(p) {
-                Ok(findings) if !findings.is_empty() => {
-                    // Count by severity
-                    for finding in &findings {
-                        match finding.severity {
-                            crate::models::Severity::Critical => critical_count += 1,
-                            crate::models::Severity::High => high_count += 1,
-                        }
-                    }
-
-                    files_with_secrets.push(FileSecrets {
-                        path: p.display().to_string(),
-                        findings,
-                    });
-                }
-                Ok(_) => {} // No findings, skip
-                Err(e) => {
-                    eprintln!("Warning: Could not scan {}: {}", p.display(), e);
-                }
+    let mut exposed_files = 0;
+
+    for file in &files_with_secrets {
+        // Count by severity
+        for finding in &file.findings {
+            match finding.severity {
+                crate::models::Severity::Critical => critical_count += 1,
+                crate::models::Severity::High => high_count += 1,
             }
         }
+        if file.exposed_mode.is_some() {
+            exposed_files += 1;
+        }
     }
 
     let total_findings = critical_count + high_count;
@@ -114,6 +131,7 @@ pub fn scan_directory(path: &str) -> Result<SecretReport> {
         total_findings,
         critical_count,
         high_count,
+        exposed_files,
         files: files_with_secrets,
     })
 }
@@ -121,6 +139,7 @@ pub fn scan_directory(path: &str) -> Result<SecretReport> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::fs;
 
     #[test]
     fn test_should_skip_line() {
@@ -135,6 +154,24 @@ mod tests {
         assert!(!should_skip_line("password: realpassword123"));
     }
 
+    #[cfg(unix)]
+    #[test]
+    fn test_exposed_mode() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("secrets.yaml");
+        fs::write(&file, "password: hunter2hunter2\n").unwrap();
+
+        fs::set_permissions(&file, fs::Permissions::from_mode(0o600)).unwrap();
+        assert_eq!(exposed_mode(&file), None);
+
+        fs::set_permissions(&file, fs::Permissions::from_mode(0o644)).unwrap();
+        assert_eq!(exposed_mode(&file).as_deref(), Some("0644"));
+
+        fs::set_permissions(&file, fs::Permissions::from_mode(0o640)).unwrap();
+        assert_eq!(exposed_mode(&file).as_deref(), Some("0640"));
+    }
+
     #[test]
     fn test_redact_secret() {
         assert_eq!(redact_secret("secretkey123456", 0, 15), "secr...");
@@ -4,16 +4,34 @@ mod patterns;
 
 use anyhow::Result;
 
+use crate::cache::{self, Cache};
 use crate::models::SecretReport;
+use crate::walk::WalkOptions;
 
 /// Scan a directory for secrets and return the report data.
-pub fn get_report(path: &str) -> Result<SecretReport> {
-    detector::scan_directory(path)
+pub fn get_report(path: &str, walk: &WalkOptions, cache: &Cache) -> Result<SecretReport> {
+    detector::scan_directory(path, walk, cache)
+}
+
+/// Fingerprint of the secret pattern set, so cached findings are dropped when it changes.
+pub(crate) fn patterns_fingerprint() -> String {
+    let patterns: Vec<_> = patterns::SECRET_PATTERNS
+        .iter()
+        .map(|p| (p.name, p.regex.as_str(), &p.severity))
+        .collect();
+    cache::fingerprint(&patterns)
 }
 
 /// Scan a directory for secrets and output results.
-pub fn scan_for_secrets(path: &str, format: &str, output_file: Option<&str>) -> Result<bool> {
-    let report = get_report(path)?;
+pub fn scan_for_secrets(
+    path: &str,
+    format: &str,
+    output_file: Option<&str>,
+    walk: &WalkOptions,
+    cache: &Cache,
+) -> Result<bool> {
+    let report = get_report(path, walk, cache)?;
+    cache.save()?;
     let has_secrets = report.total_findings > 0;
 
     match format {
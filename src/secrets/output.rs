@@ -41,6 +41,15 @@ pub fn output_terminal(report: &SecretReport) -> Result<()> {
     // Display findings
     for file in &report.files {
         writeln!(&mut stdout, "File: {}", file.path)?;
+        if let Some(mode) = &file.exposed_mode {
+            stdout.set_color(ColorSpec::new().set_fg(Some(Color::Yellow)))?;
+            writeln!(
+                &mut stdout,
+                "  Warning: readable by group/others (mode {})",
+                mode
+            )?;
+            stdout.reset()?;
+        }
 
         for finding in &file.findings {
             write!(&mut stdout, "  ")?;
@@ -79,6 +88,13 @@ pub fn output_terminal(report: &SecretReport) -> Result<()> {
         report.files_with_secrets
     )?;
     writeln!(&mut stdout, "  Total findings: {}", report.total_findings)?;
+    if report.exposed_files > 0 {
+        writeln!(
+            &mut stdout,
+            "  Group/world-readable files with secrets: {}",
+            report.exposed_files
+        )?;
+    }
     writeln!(&mut stdout)?;
 
     write!(&mut stdout, "  By severity: ")?;
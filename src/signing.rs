@@ -0,0 +1,257 @@
+use anyhow::{Context, Result, bail, ensure};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::Path;
+
+use crate::ledger::models::PruneAnchor;
+use crate::models::{Snapshot, SnapshotSignature};
+
+const ALGORITHM: &str = "ed25519";
+
+/// Generate an Ed25519 key pair. The secret key is written to `out`
+/// and the public key to `<out>.pub`, both hex-encoded.
+pub fn generate_keypair(out: &str) -> Result<()> {
+    let mut seed = [0u8; 32];
+    getrandom::fill(&mut seed).map_err(|e| anyhow::anyhow!("Failed to generate key: {}", e))?;
+    let signing_key = SigningKey::from_bytes(&seed);
+    let pub_path = format!("{}.pub", out);
+
+    write_secret_file(Path::new(out), &hex::encode(signing_key.to_bytes()))?;
+    fs::write(
+        &pub_path,
+        hex::encode(signing_key.verifying_key().to_bytes()),
+    )
+    .with_context(|| format!("Failed to write public key: {}", pub_path))?;
+
+    println!("Wrote signing key to {}", out);
+    println!("Wrote public key to {}", pub_path);
+    println!("Fingerprint: {}", fingerprint(&signing_key.verifying_key()));
+    Ok(())
+}
+
+/// Load a hex-encoded Ed25519 secret key from disk.
+pub fn load_signing_key(path: &str) -> Result<SigningKey> {
+    let bytes = read_key_bytes(path)?;
+    Ok(SigningKey::from_bytes(&bytes))
+}
+
+/// Load a hex-encoded Ed25519 public key from disk.
+pub fn load_verifying_key(path: &str) -> Result<VerifyingKey> {
+    let bytes = read_key_bytes(path)?;
+    VerifyingKey::from_bytes(&bytes).with_context(|| format!("Invalid public key: {}", path))
+}
+
+/// SHA-256 fingerprint of a public key, used to identify the signer.
+pub fn fingerprint(key: &VerifyingKey) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(key.to_bytes());
+    format!("SHA256:{:x}", hasher.finalize())
+}
+
+/// Sign a snapshot in place. Any existing signature is replaced.
+pub fn sign_snapshot(snapshot: &mut Snapshot, key: &SigningKey) -> Result<()> {
+    snapshot.signature = Some(sign(snapshot, key)?);
+    Ok(())
+}
+
+/// Sign a ledger's prune anchor in place, like a snapshot.
+pub fn sign_anchor(anchor: &mut PruneAnchor, key: &SigningKey) -> Result<()> {
+    anchor.signature = Some(sign(anchor, key)?);
+    Ok(())
+}
+
+/// Verify that a snapshot is signed by `trusted_key`. Unsigned snapshots and
+/// snapshots signed by any other key are refused.
+///
+/// The public key stored in the snapshot only names the signer. Anyone who can edit
+/// the file can also replace it, so a signature is only checked against a key the
+/// caller already trusts.
+pub fn verify_snapshot(snapshot: &Snapshot, trusted_key: &VerifyingKey) -> Result<()> {
+    verify(
+        "Snapshot",
+        snapshot,
+        snapshot.signature.as_ref(),
+        trusted_key,
+    )
+}
+
+/// Verify that a prune anchor is signed by `trusted_key`, like `verify_snapshot`.
+pub fn verify_anchor(anchor: &PruneAnchor, trusted_key: &VerifyingKey) -> Result<()> {
+    verify(
+        "Prune anchor",
+        anchor,
+        anchor.signature.as_ref(),
+        trusted_key,
+    )
+}
+
+fn sign(value: &impl Serialize, key: &SigningKey) -> Result<SnapshotSignature> {
+    let signature = key.sign(&signing_payload(value)?);
+    let verifying_key = key.verifying_key();
+    Ok(SnapshotSignature {
+        algorithm: ALGORITHM.to_string(),
+        public_key: hex::encode(verifying_key.to_bytes()),
+        fingerprint: fingerprint(&verifying_key),
+        signature: hex::encode(signature.to_bytes()),
+    })
+}
+
+fn verify(
+    what: &str,
+    value: &impl Serialize,
+    sig: Option<&SnapshotSignature>,
+    trusted_key: &VerifyingKey,
+) -> Result<()> {
+    let Some(sig) = sig else {
+        bail!("{} is not signed", what);
+    };
+    ensure!(
+        sig.algorithm == ALGORITHM,
+        "Unsupported signature algorithm: {}",
+        sig.algorithm
+    );
+    ensure!(
+        sig.public_key == hex::encode(trusted_key.to_bytes()),
+        "{} was signed by {}, expected {}",
+        what,
+        sig.fingerprint,
+        fingerprint(trusted_key)
+    );
+
+    let signature = Signature::from_bytes(&decode_array(&sig.signature)?);
+    trusted_key
+        .verify(&signing_payload(value)?, &signature)
+        .map_err(|_| anyhow::anyhow!("{} signature is invalid (content was modified)", what))
+}
+
+/// The bytes covered by a signature: the value without its `signature` field, as
+/// compact JSON with object keys sorted (`serde_json::Value` keeps maps ordered by key).
+/// Signed files with fields the model does not know are refused when loading, so
+/// every field of a signed file is covered.
+fn signing_payload(value: &impl Serialize) -> Result<Vec<u8>> {
+    let mut unsigned = serde_json::to_value(value)?;
+    if let Some(fields) = unsigned.as_object_mut() {
+        fields.remove("signature");
+    }
+    Ok(serde_json::to_vec(&unsigned)?)
+}
+
+fn read_key_bytes(path: &str) -> Result<[u8; 32]> {
+    let content =
+        fs::read_to_string(path).with_context(|| format!("Failed to read key file: {}", path))?;
+    decode_array(content.trim()).with_context(|| format!("Invalid key file: {}", path))
+}
+
+fn decode_array<const N: usize>(s: &str) -> Result<[u8; N]> {
+    let bytes = hex::decode(s).context("Invalid hex encoding")?;
+    bytes
+        .try_into()
+        .map_err(|b: Vec<u8>| anyhow::anyhow!("Expected {} bytes, got {}", N, b.len()))
+}
+
+fn write_secret_file(path: &Path, content: &str) -> Result<()> {
+    #[cfg(unix)]
+    {
+        use std::io::Write;
+        use std::os::unix::fs::OpenOptionsExt;
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(path)
+            .with_context(|| format!("Failed to write signing key: {}", path.display()))?;
+        file.write_all(content.as_bytes())?;
+    }
+    #[cfg(not(unix))]
+    fs::write(path, content)
+        .with_context(|| format!("Failed to write signing key: {}", path.display()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Entry;
+
+    fn test_key(seed: u8) -> SigningKey {
+        SigningKey::from_bytes(&[seed; 32])
+    }
+
+    fn test_snapshot() -> Snapshot {
+        Snapshot {
+            created_at: "2025-01-15T00:00:00+00:00".to_string(),
+            entries: vec![Entry {
+                path: "config.yaml".to_string(),
+                hash: "abc123".to_string(),
+                canonical_hash: None,
+                keys: None,
+                metadata: None,
+            }],
+            git_commit: None,
+            previous_hash: None,
+            signature: None,
+        }
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let key = test_key(1);
+        let mut snapshot = test_snapshot();
+        sign_snapshot(&mut snapshot, &key).unwrap();
+        assert!(verify_snapshot(&snapshot, &key.verifying_key()).is_ok());
+
+        // The signature survives a round trip through the file format
+        let loaded: Snapshot =
+            serde_json::from_slice(&serde_json::to_vec_pretty(&snapshot).unwrap()).unwrap();
+        assert!(verify_snapshot(&loaded, &key.verifying_key()).is_ok());
+    }
+
+    #[test]
+    fn test_tampered_snapshot_rejected() {
+        let key = test_key(1);
+        let mut snapshot = test_snapshot();
+        sign_snapshot(&mut snapshot, &key).unwrap();
+        snapshot.entries[0].hash = "def456".to_string();
+        let err = verify_snapshot(&snapshot, &key.verifying_key()).unwrap_err();
+        assert!(err.to_string().contains("modified"));
+    }
+
+    #[test]
+    fn test_unsigned_snapshot_rejected() {
+        let key = test_key(1);
+        let snapshot = test_snapshot();
+        let err = verify_snapshot(&snapshot, &key.verifying_key()).unwrap_err();
+        assert!(err.to_string().contains("not signed"));
+    }
+
+    #[test]
+    fn test_wrong_key_rejected() {
+        let mut snapshot = test_snapshot();
+        sign_snapshot(&mut snapshot, &test_key(1)).unwrap();
+        let err = verify_snapshot(&snapshot, &test_key(2).verifying_key()).unwrap_err();
+        assert!(err.to_string().contains("expected"));
+    }
+
+    #[test]
+    fn test_resigned_with_other_key_rejected() {
+        // Whoever edits a snapshot can re-sign it with their own key
+        let mut snapshot = test_snapshot();
+        sign_snapshot(&mut snapshot, &test_key(1)).unwrap();
+        snapshot.entries[0].hash = "def456".to_string();
+        sign_snapshot(&mut snapshot, &test_key(2)).unwrap();
+        assert!(verify_snapshot(&snapshot, &test_key(1).verifying_key()).is_err());
+    }
+
+    #[test]
+    fn test_unknown_fields_rejected() {
+        let mut snapshot = serde_json::to_value(test_snapshot()).unwrap();
+        snapshot["approved_by"] = "ops".into();
+        assert!(serde_json::from_value::<Snapshot>(snapshot.clone()).is_err());
+        snapshot.as_object_mut().unwrap().remove("approved_by");
+        snapshot["entries"][0]["owner"] = "root".into();
+        assert!(serde_json::from_value::<Snapshot>(snapshot).is_err());
+    }
+}
@@ -0,0 +1,124 @@
+use anyhow::{Context, Result};
+use std::borrow::Cow;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::format::ConfigFormat;
+use crate::models::FileMetadata;
+use crate::policy::parser::{
+    FlatMap, SourceMap, parse_config_content, parse_config_file, parse_config_source_map,
+};
+use crate::utils::{file_metadata, hash_bytes, hash_file};
+
+/// Separator between an archive's path and a member inside it,
+/// e.g. `bundle.tar.gz!/etc/app/config.yaml`.
+pub const ARCHIVE_SEPARATOR: &str = "!/";
+
+/// A config file found by a walker: either on disk or a member of an archive.
+/// Archive members are extracted into memory, so every consumer reads
+/// content through this type rather than from the filesystem.
+#[derive(Debug, Clone)]
+pub enum ConfigFile {
+    Disk(PathBuf),
+    Member {
+        /// Virtual path: `<archive>!/<path inside archive>`.
+        path: PathBuf,
+        content: Arc<[u8]>,
+    },
+}
+
+impl ConfigFile {
+    pub fn member(archive: &Path, inner: &str, content: Vec<u8>) -> ConfigFile {
+        ConfigFile::Member {
+            path: PathBuf::from(format!(
+                "{}{}{}",
+                archive.display(),
+                ARCHIVE_SEPARATOR,
+                inner
+            )),
+            content: content.into(),
+        }
+    }
+
+    /// Path used for display, glob matching and cache keys.
+    pub fn path(&self) -> &Path {
+        match self {
+            ConfigFile::Disk(p) => p,
+            ConfigFile::Member { path, .. } => path,
+        }
+    }
+
+    pub fn display(&self) -> String {
+        self.path().display().to_string()
+    }
+
+    /// Format from the file name, or from the content for a file without an extension.
+    pub fn format(&self) -> Option<ConfigFormat> {
+        ConfigFormat::from_path(self.path()).or_else(|| {
+            let content = self.read().ok()?;
+            ConfigFormat::detect(self.path(), &content)
+        })
+    }
+
+    pub fn read(&self) -> Result<Cow<'_, [u8]>> {
+        match self {
+            ConfigFile::Disk(p) => {
+                Ok(Cow::Owned(fs::read(p).with_context(|| {
+                    format!("Failed to read {}", p.display())
+                })?))
+            }
+            ConfigFile::Member { content, .. } => Ok(Cow::Borrowed(content)),
+        }
+    }
+
+    pub fn read_to_string(&self) -> Result<String> {
+        String::from_utf8(self.read()?.into_owned())
+            .with_context(|| format!("{} is not valid UTF-8", self.display()))
+    }
+
+    /// SHA-256 of the raw content.
+    pub fn hash(&self) -> Result<String> {
+        match self {
+            ConfigFile::Disk(p) => hash_file(p),
+            ConfigFile::Member { content, .. } => Ok(hash_bytes(content)),
+        }
+    }
+
+    /// Parse into a flat key-value map with the parser for the file's format.
+    pub fn parse(&self) -> Result<FlatMap> {
+        match self {
+            ConfigFile::Disk(p) => parse_config_file(p),
+            ConfigFile::Member { .. } => parse_config_content(&self.read_to_string()?, self.path())
+                .with_context(|| format!("Failed to parse: {}", self.display())),
+        }
+    }
+
+    /// Line and column of each flattened key; empty when the format has no spans.
+    pub fn source_map(&self) -> SourceMap {
+        self.read_to_string()
+            .map(|content| parse_config_source_map(&content, self.path()))
+            .unwrap_or_default()
+    }
+
+    /// Filesystem metadata; archive members have none.
+    pub fn metadata(&self) -> Option<FileMetadata> {
+        match self {
+            ConfigFile::Disk(p) => file_metadata(p).ok(),
+            ConfigFile::Member { .. } => None,
+        }
+    }
+
+    /// Path of the file on disk, or None for an archive member.
+    pub fn disk_path(&self) -> Option<&Path> {
+        match self {
+            ConfigFile::Disk(p) => Some(p),
+            ConfigFile::Member { .. } => None,
+        }
+    }
+}
+
+/// The on-disk file behind a (possibly virtual) path: the archive for a member.
+pub fn backing_file(path: &str) -> &Path {
+    Path::new(path.split(ARCHIVE_SEPARATOR).next().unwrap_or(path))
+}
@@ -1,19 +1,161 @@
 use anyhow::Result;
+use chrono::{DateTime, Utc};
 use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
 use std::{fs, path::Path};
 
+use crate::format::{self, ConfigFormat};
+use crate::models::{FileMetadata, FlatValue};
+use crate::policy::parser::FlatMap;
+
 /// Compute SHA-256 hash of a file
 pub fn hash_file(p: &Path) -> Result<String> {
-    let data = fs::read(p)?;
+    Ok(hash_bytes(&fs::read(p)?))
+}
+
+/// Compute SHA-256 hash of in-memory content, e.g. a git blob.
+pub fn hash_bytes(data: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(data);
+    format!("{:x}", hasher.finalize())
+}
+
+/// Compute a SHA-256 over a file's parsed content rather than its bytes.
+/// Keys are sorted, so reordering, reindenting or comments do not change it.
+pub fn hash_flat_map(map: &FlatMap) -> String {
+    let sorted: BTreeMap<&String, &FlatValue> = map.iter().collect();
     let mut hasher = Sha256::new();
-    hasher.update(&data);
-    Ok(format!("{:x}", hasher.finalize()))
+    // Values serialize with their type, so `port: "5432"` -> `port: 5432` changes the hash
+    hasher.update(serde_json::to_vec(&sorted).unwrap_or_default());
+    format!("{:x}", hasher.finalize())
+}
+
+/// Read permissions, ownership, size, mtime and symlink target of a file.
+/// Mode and ownership describe the link target when `p` is a symlink.
+pub fn file_metadata(p: &Path) -> Result<FileMetadata> {
+    let meta = fs::metadata(p)?;
+    let symlink_target = if fs::symlink_metadata(p)?.file_type().is_symlink() {
+        Some(fs::read_link(p)?.display().to_string())
+    } else {
+        None
+    };
+    let mtime = meta
+        .modified()
+        .ok()
+        .map(|t| DateTime::<Utc>::from(t).to_rfc3339());
+
+    #[cfg(unix)]
+    let (mode, uid, gid) = {
+        use std::os::unix::fs::MetadataExt;
+        (
+            Some(meta.mode() & 0o7777),
+            Some(meta.uid()),
+            Some(meta.gid()),
+        )
+    };
+    #[cfg(not(unix))]
+    let (mode, uid, gid) = (None, None, None);
+
+    Ok(FileMetadata {
+        mode,
+        uid,
+        gid,
+        size: meta.len(),
+        mtime,
+        symlink_target,
+    })
+}
+
+/// Format permission bits the way `chmod` takes them, e.g. `0644`.
+pub fn format_mode(mode: u32) -> String {
+    format!("{:04o}", mode)
 }
 
 /// Check if a file is a supported config format
 pub fn is_config(p: &Path) -> bool {
-    matches!(
-        p.extension().and_then(|s| s.to_str()),
-        Some("yml" | "yaml" | "json" | "toml")
-    )
+    ConfigFormat::from_path(p).is_some()
+}
+
+/// Check if a file is a supported config format by name or, when it has no
+/// extension, by content. `read` is only called for files that may be sniffed.
+pub fn is_config_content(p: &Path, read: impl FnOnce() -> Option<Vec<u8>>) -> bool {
+    is_config(p)
+        || (ConfigFormat::sniffable(p) && read().is_some_and(|c| format::sniff(&c).is_some()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::policy::parser::parse_config_file;
+    use std::io::Write;
+
+    fn canonical_hash(p: &Path) -> Result<String> {
+        Ok(hash_flat_map(&parse_config_file(p)?))
+    }
+
+    fn write_temp_file(content: &str, extension: &str) -> tempfile::NamedTempFile {
+        let mut file = tempfile::Builder::new()
+            .suffix(&format!(".{}", extension))
+            .tempfile()
+            .unwrap();
+        file.write_all(content.as_bytes()).unwrap();
+        file
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_file_metadata_mode_and_symlink() {
+        use std::os::unix::fs::{PermissionsExt, symlink};
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("db.yaml");
+        fs::write(&file, "password: x\n").unwrap();
+        fs::set_permissions(&file, fs::Permissions::from_mode(0o640)).unwrap();
+        let link = dir.path().join("link.yaml");
+        symlink(&file, &link).unwrap();
+
+        let meta = file_metadata(&file).unwrap();
+        assert_eq!(meta.mode, Some(0o640));
+        assert_eq!(meta.size, 12);
+        assert!(meta.mtime.is_some());
+        assert_eq!(meta.symlink_target, None);
+
+        let link_meta = file_metadata(&link).unwrap();
+        assert_eq!(link_meta.mode, Some(0o640));
+        assert_eq!(
+            link_meta.symlink_target.as_deref(),
+            Some(file.display().to_string().as_str())
+        );
+        assert_eq!(format_mode(0o640), "0640");
+    }
+
+    #[test]
+    fn test_canonical_hash_ignores_formatting() {
+        let a = write_temp_file("app:\n  name: test\n  port: 8080\n", "yaml");
+        let b = write_temp_file("# comment\napp:\n    port: 8080\n    name: test\n", "yaml");
+        assert_ne!(hash_file(a.path()).unwrap(), hash_file(b.path()).unwrap());
+        assert_eq!(
+            canonical_hash(a.path()).unwrap(),
+            canonical_hash(b.path()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_canonical_hash_same_across_formats() {
+        let yaml = write_temp_file("app:\n  name: test\n", "yaml");
+        let json = write_temp_file(r#"{"app": {"name": "test"}}"#, "json");
+        assert_eq!(
+            canonical_hash(yaml.path()).unwrap(),
+            canonical_hash(json.path()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_canonical_hash_detects_value_change() {
+        let a = write_temp_file("app:\n  port: 8080\n", "yaml");
+        let b = write_temp_file("app:\n  port: 9090\n", "yaml");
+        assert_ne!(
+            canonical_hash(a.path()).unwrap(),
+            canonical_hash(b.path()).unwrap()
+        );
+    }
 }
@@ -0,0 +1,150 @@
+use anyhow::Result;
+
+use crate::cache::Cache;
+use crate::diff::{compare_snapshots, load_snapshot, output};
+use crate::scan::collect_snapshot;
+use crate::signing;
+use crate::walk::WalkOptions;
+
+/// Verify a live directory against a baseline snapshot.
+/// Returns true if drift was detected (new, missing or modified files).
+#[allow(clippy::too_many_arguments)]
+pub fn verify(
+    path: &str,
+    snapshot_path: &str,
+    format: &str,
+    output_file: Option<&str>,
+    verify_key: Option<&str>,
+    ignore_cosmetic: bool,
+    walk: &WalkOptions,
+    cache: &Cache,
+) -> Result<bool> {
+    let trusted_key = verify_key.map(signing::load_verifying_key).transpose()?;
+    let baseline = load_snapshot(snapshot_path, trusted_key.as_ref())?;
+    // Only embed keys when the baseline can be compared at the key level
+    let with_keys = baseline.entries.iter().any(|e| e.keys.is_some());
+    let live = collect_snapshot(path, with_keys, walk, cache)?;
+    cache.save()?;
+
+    let mut report = compare_snapshots(&baseline, &live, snapshot_path, path);
+    if ignore_cosmetic {
+        report = report.without_cosmetic();
+    }
+    let has_drift = report.has_changes();
+
+    match format {
+        "json" => output::output_json(&report, output_file)?,
+        "markdown" | "md" => {
+            output::output_markdown(&report, "ConfigTrace Drift Check", output_file)?
+        }
+        _ => output::output_verify_terminal(&report)?,
+    }
+
+    Ok(has_drift)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+    use std::fs;
+    use std::path::Path;
+
+    /// Write a baseline of `root` and return its path.
+    fn baseline(root: &Path, out: &Path) -> String {
+        let snapshot = collect_snapshot(
+            root.to_str().unwrap(),
+            false,
+            &WalkOptions::default(),
+            &Cache::open(None).unwrap(),
+        )
+        .unwrap();
+        fs::write(out, serde_json::to_vec(&snapshot).unwrap()).unwrap();
+        out.display().to_string()
+    }
+
+    /// Run `verify` with JSON output and return whether it found drift, and the report.
+    fn run(root: &Path, baseline: &str, out: &Path) -> (bool, Value) {
+        let out = out.to_str().unwrap();
+        let has_drift = verify(
+            root.to_str().unwrap(),
+            baseline,
+            "json",
+            Some(out),
+            None,
+            false,
+            &WalkOptions::default(),
+            &Cache::open(None).unwrap(),
+        )
+        .unwrap();
+        (
+            has_drift,
+            serde_json::from_slice(&fs::read(out).unwrap()).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_verify_detects_drift() {
+        let root = tempfile::tempdir().unwrap();
+        let work = tempfile::tempdir().unwrap();
+        let out = work.path().join("report.json");
+        fs::write(root.path().join("app.yaml"), "debug: false\n").unwrap();
+        fs::write(root.path().join("db.yaml"), "host: db\n").unwrap();
+        let snapshot = baseline(root.path(), &work.path().join("baseline.json"));
+
+        let (has_drift, report) = run(root.path(), &snapshot, &out);
+        assert!(!has_drift);
+        assert_eq!(report["total_changed"], 0);
+
+        fs::write(root.path().join("app.yaml"), "debug: true\n").unwrap();
+        fs::remove_file(root.path().join("db.yaml")).unwrap();
+        fs::write(root.path().join("new.json"), "{}").unwrap();
+        let (has_drift, report) = run(root.path(), &snapshot, &out);
+        assert!(has_drift);
+        assert_eq!(report["total_changed"], 1);
+        assert!(
+            report["changed"][0]["path"]
+                .as_str()
+                .unwrap()
+                .ends_with("app.yaml")
+        );
+        assert_eq!(report["total_removed"], 1);
+        assert!(
+            report["removed"][0]["path"]
+                .as_str()
+                .unwrap()
+                .ends_with("db.yaml")
+        );
+        assert_eq!(report["total_added"], 1);
+        assert!(
+            report["added"][0]["path"]
+                .as_str()
+                .unwrap()
+                .ends_with("new.json")
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_verify_detects_metadata_only_drift() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let root = tempfile::tempdir().unwrap();
+        let work = tempfile::tempdir().unwrap();
+        let out = work.path().join("report.json");
+        let file = root.path().join("secrets.yaml");
+        fs::write(&file, "token: x\n").unwrap();
+        fs::set_permissions(&file, fs::Permissions::from_mode(0o600)).unwrap();
+        let snapshot = baseline(root.path(), &work.path().join("baseline.json"));
+
+        fs::set_permissions(&file, fs::Permissions::from_mode(0o644)).unwrap();
+        let (has_drift, report) = run(root.path(), &snapshot, &out);
+        assert!(has_drift);
+        assert_eq!(report["total_changed"], 0);
+        assert_eq!(report["total_metadata_changed"], 1);
+        let change = &report["metadata_changed"][0]["metadata_changes"][0];
+        assert_eq!(change["field"], "mode");
+        assert_eq!(change["old_value"], "0600");
+        assert_eq!(change["new_value"], "0644");
+    }
+}
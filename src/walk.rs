@@ -0,0 +1,262 @@
+use anyhow::Result;
+use clap::Args;
+use ignore::WalkBuilder;
+use ignore::overrides::{Override, OverrideBuilder};
+use rayon::prelude::*;
+use std::path::{Path, PathBuf};
+
+use crate::archive;
+use crate::format::MAX_SNIFF_BYTES;
+use crate::source::ConfigFile;
+use crate::utils::is_config_content;
+
+/// Name of the per-directory ignore file, using gitignore syntax.
+pub const IGNORE_FILE: &str = ".configtraceignore";
+
+/// File selection shared by every command that walks a directory,
+/// so `scan`, `secrets`, `policy check` and `report` see the same file set.
+#[derive(Args, Debug, Clone, Default)]
+pub struct WalkOptions {
+    /// Only include files matching this glob (repeatable, gitignore syntax)
+    #[arg(long = "include", value_name = "GLOB")]
+    pub include: Vec<String>,
+    /// Skip files and directories matching this glob (repeatable, gitignore syntax)
+    #[arg(long = "exclude", value_name = "GLOB")]
+    pub exclude: Vec<String>,
+    /// Also skip files ignored by .gitignore
+    #[arg(long)]
+    pub gitignore: bool,
+}
+
+/// Collect every supported config file under `path`, sorted by path.
+///
+/// `.configtraceignore` files are always honoured; `.gitignore` only with `--gitignore`.
+/// Archives (`.tar`, `.tar.gz`, `.tgz`, `.zip`) are walked as virtual directories,
+/// and `--include`/`--exclude` apply to the paths inside them as well.
+/// Callers process the list on the rayon pool; collecting parallel results
+/// back in this order keeps snapshots and reports deterministic.
+pub fn config_files(path: &str, options: &WalkOptions) -> Result<Vec<ConfigFile>> {
+    let mut files = Vec::new();
+    let mut archives: Vec<PathBuf> = Vec::new();
+    for p in WalkBuilder::new(path)
+        .standard_filters(false)
+        .add_custom_ignore_filename(IGNORE_FILE)
+        .git_ignore(options.gitignore)
+        .git_exclude(options.gitignore)
+        .require_git(false)
+        .overrides(overrides(path, options)?)
+        .build()
+        .filter_map(|e| e.ok())
+        .map(|e| e.into_path())
+        .filter(|p| p.is_file())
+    {
+        if is_config_content(&p, || read_small(&p)) {
+            files.push(ConfigFile::Disk(p));
+        } else if archive::is_archive(&p) {
+            archives.push(p);
+        }
+    }
+
+    let member_overrides = overrides("", options)?;
+    let members: Vec<ConfigFile> = archives
+        .par_iter()
+        .flat_map_iter(|a| match archive::read_members(a) {
+            Ok(members) => members
+                .into_iter()
+                .filter(|(inner, _)| is_selected(&member_overrides, inner))
+                .map(|(inner, content)| ConfigFile::member(a, &inner, content))
+                .collect(),
+            Err(e) => {
+                eprintln!("Warning: Could not read archive {}: {:#}", a.display(), e);
+                Vec::new()
+            }
+        })
+        .collect();
+
+    files.extend(members);
+    files.sort_by(|a, b| a.path().cmp(b.path()));
+    Ok(files)
+}
+
+/// Content of a file small enough to sniff.
+fn read_small(p: &Path) -> Option<Vec<u8>> {
+    let size = p.metadata().ok()?.len();
+    (size <= MAX_SNIFF_BYTES)
+        .then(|| std::fs::read(p).ok())
+        .flatten()
+}
+
+/// Build the `--include`/`--exclude` matcher for paths relative to `root`.
+pub fn overrides(root: &str, options: &WalkOptions) -> Result<Override> {
+    let mut builder = OverrideBuilder::new(root);
+    for glob in &options.include {
+        builder.add(glob)?;
+    }
+    for glob in &options.exclude {
+        builder.add(&format!("!{}", glob))?;
+    }
+    Ok(builder.build()?)
+}
+
+/// Whether a file path (relative to the override root) passes `--include`/`--exclude`.
+/// Used where there is no directory to walk, such as git trees.
+pub fn is_selected(overrides: &Override, path: &str) -> bool {
+    let path = Path::new(path);
+    if overrides.matched(path, false).is_ignore() {
+        return false;
+    }
+    // An excluded directory excludes everything below it
+    !path
+        .ancestors()
+        .skip(1)
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .any(|dir| overrides.matched(dir, true).is_ignore())
+}
+
+/// Size the global worker pool used for hashing, parsing and scanning.
+/// Zero uses one worker per CPU.
+pub fn init_jobs(jobs: usize) -> Result<()> {
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(jobs)
+        .build_global()
+        .map_err(|e| anyhow::anyhow!("Failed to start worker pool: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn names(root: &Path, options: &WalkOptions) -> Vec<String> {
+        config_files(root.to_str().unwrap(), options)
+            .unwrap()
+            .iter()
+            .map(|f| f.path().strip_prefix(root).unwrap().display().to_string())
+            .collect()
+    }
+
+    fn fixture() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        for sub in ["app", "node_modules/pkg", "fixtures", ".github"] {
+            fs::create_dir_all(root.join(sub)).unwrap();
+        }
+        fs::write(root.join("app/config.yaml"), "a: 1").unwrap();
+        fs::write(root.join("app/settings.json"), "{}").unwrap();
+        fs::write(root.join("node_modules/pkg/package.json"), "{}").unwrap();
+        fs::write(root.join("fixtures/bad.yaml"), "a: 1").unwrap();
+        fs::write(root.join(".github/ci.yml"), "a: 1").unwrap();
+        fs::write(root.join("readme.md"), "# hi").unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_config_files_sorted_and_filtered() {
+        let dir = fixture();
+        assert_eq!(
+            names(dir.path(), &WalkOptions::default()),
+            vec![
+                ".github/ci.yml",
+                "app/config.yaml",
+                "app/settings.json",
+                "fixtures/bad.yaml",
+                "node_modules/pkg/package.json",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_extensionless_files_are_sniffed() {
+        let dir = fixture();
+        let root = dir.path();
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::write(root.join("app/Appfile"), "server:\n  port: 8080\n").unwrap();
+        fs::write(root.join("app/NOTES"), "Remember to rotate keys.\n").unwrap();
+        fs::write(root.join(".git/config"), "[core]\nbare = false\n").unwrap();
+
+        let files = names(root, &WalkOptions::default());
+        assert!(files.contains(&"app/Appfile".to_string()));
+        assert!(!files.contains(&"app/NOTES".to_string()));
+        assert!(!files.iter().any(|f| f.starts_with(".git/")));
+    }
+
+    #[test]
+    fn test_configtraceignore_and_gitignore() {
+        let dir = fixture();
+        fs::write(dir.path().join(IGNORE_FILE), "node_modules/\n").unwrap();
+        fs::write(dir.path().join(".gitignore"), "fixtures/\n").unwrap();
+
+        let files = names(dir.path(), &WalkOptions::default());
+        assert!(!files.iter().any(|f| f.starts_with("node_modules")));
+        assert!(files.contains(&"fixtures/bad.yaml".to_string()));
+
+        let options = WalkOptions {
+            gitignore: true,
+            ..Default::default()
+        };
+        let files = names(dir.path(), &options);
+        assert!(!files.iter().any(|f| f.starts_with("fixtures")));
+    }
+
+    #[test]
+    fn test_is_selected_for_tree_paths() {
+        let options = WalkOptions {
+            include: vec!["*.yaml".to_string()],
+            exclude: vec!["fixtures/".to_string()],
+            gitignore: false,
+        };
+        let overrides = overrides("", &options).unwrap();
+        assert!(is_selected(&overrides, "app/config.yaml"));
+        assert!(!is_selected(&overrides, "app/settings.json"));
+        assert!(!is_selected(&overrides, "fixtures/bad.yaml"));
+        assert!(!is_selected(&overrides, "tests/fixtures/bad.yaml"));
+    }
+
+    #[test]
+    fn test_include_and_exclude_globs() {
+        let dir = fixture();
+        let options = WalkOptions {
+            include: vec!["*.yaml".to_string(), "*.yml".to_string()],
+            exclude: vec!["fixtures/".to_string()],
+            gitignore: false,
+        };
+        assert_eq!(
+            names(dir.path(), &options),
+            vec![".github/ci.yml", "app/config.yaml"]
+        );
+    }
+
+    #[test]
+    fn test_archive_members_are_walked() {
+        let dir = fixture();
+        let mut builder = tar::Builder::new(Vec::new());
+        for name in ["etc/app.yaml", "fixtures/skip.yaml"] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(4);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, name, &b"a: 1"[..])
+                .unwrap();
+        }
+        fs::write(
+            dir.path().join("app/bundle.tar"),
+            builder.into_inner().unwrap(),
+        )
+        .unwrap();
+
+        let options = WalkOptions {
+            exclude: vec!["fixtures/".to_string(), "node_modules/".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            names(dir.path(), &options),
+            vec![
+                ".github/ci.yml",
+                "app/bundle.tar!/etc/app.yaml",
+                "app/config.yaml",
+                "app/settings.json",
+            ]
+        );
+    }
+}
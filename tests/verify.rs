@@ -0,0 +1,29 @@
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+fn configtrace(args: &[&str], dir: &Path) -> i32 {
+    Command::new(env!("CARGO_BIN_EXE_configtrace"))
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap()
+        .status
+        .code()
+        .unwrap()
+}
+
+#[test]
+fn test_verify_exit_codes() {
+    let work = tempfile::tempdir().unwrap();
+    fs::create_dir(work.path().join("configs")).unwrap();
+    fs::write(work.path().join("configs/app.yaml"), "debug: false\n").unwrap();
+
+    let scan = ["scan", "configs", "--out", "baseline.json"];
+    assert_eq!(configtrace(&scan, work.path()), 0);
+    let verify = ["verify", "configs", "--snapshot", "baseline.json"];
+    assert_eq!(configtrace(&verify, work.path()), 0);
+
+    fs::write(work.path().join("configs/app.yaml"), "debug: true\n").unwrap();
+    assert_eq!(configtrace(&verify, work.path()), 1);
+}